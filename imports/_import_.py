@@ -0,0 +1,59 @@
+"""Мост для pyimport: вызывает функцию модуля Python по запросу программы CvadroScript.
+
+Запуск: _import_.py <каталог-hex> <модуль> <функция> [аргумент ...]
+
+Каталог программы передаётся в шестнадцатеричном виде. Модуль ищется в нём
+и в его подкаталоге imports/. Каждый аргумент имеет вид <метка>:<значение>,
+где метка — i (int), f (float), b (bool) или s (строка в UTF-8, hex).
+Результат функции печатается в stdout без перевода строки: bool как 1/0,
+float через repr, строки как есть. При ошибке код возврата ненулевой.
+"""
+
+import importlib
+import os
+import sys
+
+
+def decode(arg):
+    kind, _, value = arg.partition(":")
+    if kind == "i":
+        return int(value)
+    if kind == "f":
+        return float(value)
+    if kind == "b":
+        return value == "1"
+    if kind == "s":
+        return bytes.fromhex(value).decode("utf-8")
+    raise ValueError("неизвестный тип аргумента: " + arg)
+
+
+def encode(value):
+    if value is None:
+        return ""
+    if isinstance(value, bool):
+        return "1" if value else "0"
+    if isinstance(value, float):
+        return repr(value)
+    return str(value)
+
+
+def main():
+    module_dir = bytes.fromhex(sys.argv[1]).decode("utf-8")
+    module_name, function_name = sys.argv[2], sys.argv[3]
+    args = [decode(arg) for arg in sys.argv[4:]]
+
+    sys.path[0:0] = [module_dir, os.path.join(module_dir, "imports")]
+    module = importlib.import_module(module_name)
+    function = getattr(module, function_name)
+
+    # Вывод самой функции не должен смешиваться с результатом
+    result_stream = sys.stdout.buffer
+    sys.stdout = sys.stderr
+    result = function(*args)
+
+    result_stream.write(encode(result).encode("utf-8"))
+    result_stream.flush()
+
+
+if __name__ == "__main__":
+    main()
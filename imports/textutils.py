@@ -0,0 +1,22 @@
+"""Модуль для pyimport_test.cst: проверяет передачу всех скалярных типов."""
+
+
+def shout(text):
+    return text.upper() + "!"
+
+
+def add(a, b):
+    return a + b
+
+
+def half(x):
+    return x / 2
+
+
+def is_even(n):
+    return n % 2 == 0
+
+
+def greet(name):
+    print("этот вывод идёт в stderr, а не в результат")
+    return "Привет, " + name
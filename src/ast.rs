@@ -1,44 +1,313 @@
+use std::fmt;
+
 #[derive(Debug, Clone)]
 pub enum Expr {
     Literal(Literal),
     Variable(String),
     BinaryOp(Box<Expr>, BinOp, Box<Expr>),
+    // Цепочка сравнений a < b < c: первый операнд и пары (оператор, операнд)
+    Compare(Box<Expr>, Vec<(BinOp, Expr)>),
     UnaryOp(UnaryOp, Box<Expr>),
-    Call(String, Vec<Expr>),
+    // Вызов: позиционные аргументы и именованные (f(1, y=2))
+    Call(Box<Expr>, Vec<Expr>, Vec<(String, Expr)>),
+    Field(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+    // Срез s[start:end]; любую из границ можно опустить
+    Slice(Box<Expr>, Option<Box<Expr>>, Option<Box<Expr>>),
+    List(Vec<Expr>),
+    Lambda(Vec<String>, Box<Expr>),
+    FString(Vec<FStringPart>),
+    // Явное приведение типа: x as u8
+    Cast(Box<Expr>, Type),
     If(Box<Expr>, Block, Option<Block>),
+    // x if cond else y: условие, значение при истинном и при ложном условии
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
     While(Box<Expr>, Block),
     For(String, Box<Expr>, Block),
 }
 
+#[derive(Debug, Clone)]
+pub enum FStringPart {
+    Text(String),
+    Expr(Expr, Option<FormatSpec>),
+}
+
+// Спецификация формата в стиле Python: [[fill]align][sign][0][width][.precision][type]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatSpec {
+    pub fill: char,
+    pub align: Option<char>,
+    pub sign: Option<char>,
+    pub width: Option<usize>,
+    pub precision: Option<usize>,
+    pub kind: Option<char>,
+}
+
 #[derive(Debug, Clone)]
 pub enum Stmt {
     Expr(Expr),
+    // Тип без аннотации у переменных верхнего уровня записывает семантический анализ
     Let(String, Option<Type>, Expr),
-    Assign(String, Expr),
+    Const(String, Option<Type>, Expr),
+    // global x: функция изменяет глобальную переменную
+    Global(Vec<String>),
+    Assign(Expr, Expr),
+    AugAssign(Expr, BinOp, Expr),
     Function(FunctionDef),
+    Struct(StructDef),
+    Import(String),
+    FromImport(String, Vec<String>),
+    PyImport(String, Vec<FunctionDef>),
     Return(Option<Expr>),
+    Raise(Option<Expr>),
+    Try(Block, Vec<ExceptClause>, Option<Block>),
     Pass,
+    // Номер строки исходного файла, с которой начинается следующая инструкция
+    Line(usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct ExceptClause {
+    pub error_type: Option<String>,
+    pub name: Option<String>,
+    pub body: Block,
 }
 
 #[derive(Debug, Clone)]
 pub struct FunctionDef {
     pub name: String,
-    pub params: Vec<(String, Type)>,
+    pub type_params: Vec<String>,
+    pub params: Vec<Param>,
     pub return_type: Type,
     pub body: Block,
 }
 
+impl FunctionDef {
+    pub fn function_type(&self) -> Type {
+        Type::Function(
+            self.params.iter().map(|param| param.ty.clone()).collect(),
+            Box::new(self.return_type.clone()),
+        )
+    }
+}
+
 #[derive(Debug, Clone)]
-pub struct Block(pub Vec<Stmt>);
+pub struct Param {
+    pub name: String,
+    // У *args это тип списка, в который собираются лишние аргументы
+    pub ty: Type,
+    pub default: Option<Expr>,
+    pub variadic: bool,
+}
 
 #[derive(Debug, Clone)]
+pub struct StructDef {
+    pub name: String,
+    pub type_params: Vec<String>,
+    pub fields: Vec<(String, Type)>,
+}
+
+impl StructDef {
+    // Параметры конструктора: по одному на каждое поле
+    pub fn constructor_params(&self) -> Vec<Param> {
+        self.fields.iter()
+            .map(|(name, ty)| Param {
+                name: name.clone(),
+                ty: ty.clone(),
+                default: None,
+                variadic: false,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Block(pub Vec<Stmt>);
+
+impl Block {
+    // Блок всегда завершается return или raise
+    pub fn always_exits(&self) -> bool {
+        let last = self.0.iter().rev().find(|stmt| !matches!(stmt, Stmt::Line(_)));
+        matches!(last, Some(Stmt::Return(_) | Stmt::Raise(_)))
+    }
+}
+
+impl Expr {
+    // Литерал, в том числе отрицательное число
+    pub fn is_literal(&self) -> bool {
+        match self {
+            Expr::Literal(_) => true,
+            Expr::UnaryOp(UnaryOp::Neg, operand) => {
+                matches!(operand.as_ref(), Expr::Literal(Literal::Int(_) | Literal::Float(_)))
+            },
+            // Литерал с суффиксом: 200u8
+            Expr::Cast(operand, _) => operand.is_literal(),
+            _ => false,
+        }
+    }
+    
+    // Значение целочисленного литерала без суффикса, в том числе отрицательного
+    pub fn int_literal(&self) -> Option<i64> {
+        match self {
+            Expr::Literal(Literal::Int(n)) => Some(*n),
+            Expr::UnaryOp(UnaryOp::Neg, operand) => match operand.as_ref() {
+                Expr::Literal(Literal::Int(n)) => Some(-n),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+    
+    // Переменная, которой принадлежит место присваивания: p для p.x[0]
+    pub fn root_variable(&self) -> Option<&str> {
+        match self {
+            Expr::Variable(name) => Some(name),
+            Expr::Field(object, _) | Expr::Index(object, _) => object.root_variable(),
+            _ => None,
+        }
+    }
+    
+    // Проверка переменной на None: x != None, x == None или None != x.
+    // Возвращает имя переменной и true, если при истинном условии она не None.
+    pub fn none_check(&self) -> Option<(&str, bool)> {
+        let (left, op, right) = match self {
+            Expr::BinaryOp(left, op, right) => (left.as_ref(), op, right.as_ref()),
+            _ => return None,
+        };
+        let name = match (left, right) {
+            (Expr::Variable(name), Expr::Literal(Literal::None)) |
+            (Expr::Literal(Literal::None), Expr::Variable(name)) => name,
+            _ => return None,
+        };
+        match op {
+            BinOp::Ne => Some((name, true)),
+            BinOp::Eq => Some((name, false)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Type {
+    // int и float — то же, что i64 и f64
     Int,
     Float,
+    I8,
+    I16,
+    I32,
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    // Символ Unicode
+    Char,
     String,
     Bool,
     Void,
+    List(Box<Type>),
+    Function(Vec<Type>, Box<Type>),
     Custom(String),
+    Generic(String, Vec<Type>),
+    // int? или Optional[int]
+    Optional(Box<Type>),
+    // Тип литерала None; в аннотациях не записывается
+    None,
+}
+
+impl Type {
+    // Встроенный числовой тип по имени
+    pub fn numeric(name: &str) -> Option<Type> {
+        Some(match name {
+            "int" | "i64" => Type::Int,
+            "float" | "f64" => Type::Float,
+            "i8" => Type::I8,
+            "i16" => Type::I16,
+            "i32" => Type::I32,
+            "u8" => Type::U8,
+            "u16" => Type::U16,
+            "u32" => Type::U32,
+            "u64" => Type::U64,
+            "f32" => Type::F32,
+            _ => return None,
+        })
+    }
+    
+    pub fn is_integer(&self) -> bool {
+        self.int_bits().is_some()
+    }
+    
+    pub fn is_float(&self) -> bool {
+        matches!(self, Type::Float | Type::F32)
+    }
+    
+    pub fn is_numeric(&self) -> bool {
+        self.is_integer() || self.is_float()
+    }
+    
+    pub fn is_unsigned(&self) -> bool {
+        matches!(self, Type::U8 | Type::U16 | Type::U32 | Type::U64)
+    }
+    
+    // Разрядность целого типа
+    pub fn int_bits(&self) -> Option<u32> {
+        match self {
+            Type::I8 | Type::U8 => Some(8),
+            Type::I16 | Type::U16 => Some(16),
+            Type::I32 | Type::U32 => Some(32),
+            Type::Int | Type::U64 => Some(64),
+            _ => None,
+        }
+    }
+    
+    // Наименьшее и наибольшее значение целого типа
+    pub fn int_range(&self) -> Option<(i128, i128)> {
+        let bits = self.int_bits()?;
+        if self.is_unsigned() {
+            Some((0, (1i128 << bits) - 1))
+        } else {
+            Some((-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1))
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::I8 => write!(f, "i8"),
+            Type::I16 => write!(f, "i16"),
+            Type::I32 => write!(f, "i32"),
+            Type::U8 => write!(f, "u8"),
+            Type::U16 => write!(f, "u16"),
+            Type::U32 => write!(f, "u32"),
+            Type::U64 => write!(f, "u64"),
+            Type::F32 => write!(f, "f32"),
+            Type::Char => write!(f, "char"),
+            Type::String => write!(f, "string"),
+            Type::Bool => write!(f, "bool"),
+            Type::Void => write!(f, "void"),
+            Type::List(item) => write!(f, "list[{}]", item),
+            Type::Function(params, return_type) => {
+                let params = params.iter()
+                    .map(|ty| ty.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "fn({}) -> {}", params, return_type)
+            },
+            Type::Custom(name) => write!(f, "{}", name),
+            Type::Generic(name, args) => {
+                let args = args.iter()
+                    .map(|ty| ty.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "{}[{}]", name, args)
+            },
+            Type::Optional(inner) => write!(f, "{}?", inner),
+            Type::None => write!(f, "None"),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -47,16 +316,50 @@ pub enum Literal {
     Float(f64),
     String(String),
     Bool(bool),
+    None,
 }
 
 #[derive(Debug, Clone)]
 pub enum BinOp {
-    Add, Sub, Mul, Div, Mod,
+    Add, Sub, Mul, Div, Mod, Pow, FloorDiv,
+    BitAnd, BitOr, BitXor, Shl, Shr,
     Eq, Ne, Lt, Gt, Le, Ge,
+    // Проверка вхождения: подстроки в строку или элемента в список
+    In, NotIn,
     And, Or,
 }
 
+impl fmt::Display for BinOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Mod => "%",
+            BinOp::Pow => "**",
+            BinOp::FloorDiv => "//",
+            BinOp::BitAnd => "&",
+            BinOp::BitOr => "|",
+            BinOp::BitXor => "^",
+            BinOp::Shl => "<<",
+            BinOp::Shr => ">>",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+            BinOp::Lt => "<",
+            BinOp::Gt => ">",
+            BinOp::Le => "<=",
+            BinOp::Ge => ">=",
+            BinOp::In => "in",
+            BinOp::NotIn => "not in",
+            BinOp::And => "and",
+            BinOp::Or => "or",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum UnaryOp {
-    Neg, Not,
+    Neg, Not, BitNot,
 }
\ No newline at end of file
@@ -1,67 +1,1442 @@
-use crate::ast::{Stmt, Expr, FunctionDef, Block, Type, Literal, BinOp, UnaryOp};
+use std::collections::HashMap;
+
+use crate::ast::{Stmt, Expr, FunctionDef, StructDef, Block, Type, Literal, BinOp, UnaryOp, FStringPart, ExceptClause, Param};
+use crate::modules::Module;
+use crate::stdlib::{STD_MODULES, std_module};
 
 pub struct CodeGen {
     output: String,
     indent_level: usize,
+    // Модули, импортированные текущим модулем: utils.f превращается в utils::f
+    imported: Vec<String>,
+    // Функции и структуры текущего модуля, включая импортированные через from
+    signatures: HashMap<String, Signature>,
+    // Функции и структуры уже сгенерированных модулей и модулей Python
+    module_signatures: HashMap<String, HashMap<String, Signature>>,
+    // Каталог текущего модуля: в нём мост ищет модули Python
+    module_dir: String,
+    // Вспомогательный код среды выполнения, который понадобился программе
+    runtime: Vec<&'static str>,
+    // Счётчик для уникальных имён вспомогательных переменных
+    temp_counter: usize,
+    // Генерируется главный модуль: его main становится cvadro_main
+    in_entry: bool,
+    // Режим --checked-arithmetic: +, -, *, / и % проверяют переполнение и деление на ноль
+    checked_arithmetic: bool,
+}
+
+// Параметры функции или конструктора структуры: по ним аргументы вызова
+// расставляются в порядке объявления
+#[derive(Clone)]
+struct Signature {
+    is_function: bool,
+    generic: bool,
+    // Вложенная функция хранится в std::function, где нет значений по умолчанию
+    is_local: bool,
+    params: Vec<Param>,
+}
+
+// Вывод типов, которые C++ печатает не числом: u8 и i8 — это unsigned char и signed char,
+// а char — символ Unicode, который выводится в UTF-8
+const STREAM_RUNTIME: &str = r#"inline std::ostream& operator<<(std::ostream& out, signed char value) {
+    return out << static_cast<int>(value);
+}
+
+inline std::ostream& operator<<(std::ostream& out, unsigned char value) {
+    return out << static_cast<unsigned>(value);
+}
+
+inline std::ostream& operator<<(std::ostream& out, char32_t value) {
+    char bytes[4];
+    int count = 1;
+    if (value < 0x80) {
+        bytes[0] = static_cast<char>(value);
+    } else if (value < 0x800) {
+        bytes[0] = static_cast<char>(0xC0 | (value >> 6));
+        count = 2;
+    } else if (value < 0x10000) {
+        bytes[0] = static_cast<char>(0xE0 | (value >> 12));
+        count = 3;
+    } else {
+        bytes[0] = static_cast<char>(0xF0 | (value >> 18));
+        count = 4;
+    }
+    for (int i = 1; i < count; i++) {
+        bytes[i] = static_cast<char>(0x80 | ((value >> (6 * (count - 1 - i))) & 0x3F));
+    }
+    return out.write(bytes, count);
+}
+
+"#;
+
+// Вывод значений как в Python: bool — True/False, float — кратчайшая точная запись,
+// списки, структуры и None. Строки и символы внутри списков и структур берутся в кавычки.
+const PRINT_RUNTIME: &str = r#"#include <charconv>
+#include <cmath>
+#include <sstream>
+#include <type_traits>
+
+template<typename T> struct cvadro_is_list : std::false_type {};
+template<typename T> struct cvadro_is_list<std::vector<T>> : std::true_type {};
+template<typename T> struct cvadro_is_optional : std::false_type {};
+template<typename T> struct cvadro_is_optional<std::optional<T>> : std::true_type {};
+template<typename T> struct cvadro_is_function : std::false_type {};
+template<typename T> struct cvadro_is_function<std::function<T>> : std::true_type {};
+
+// Структуры программы печатают свои поля сами: Point(x=1, y=2)
+template<typename T, typename = void> struct cvadro_has_fields : std::false_type {};
+template<typename T>
+struct cvadro_has_fields<T, std::void_t<decltype(std::declval<const T&>().cvadro_write_fields(std::declval<std::ostream&>()))>>
+    : std::true_type {};
+
+// Кратчайшая запись, которая читается обратно в то же число; 1.0, а не 1
+template<typename T>
+std::string cvadro_float_repr(T value) {
+    if (std::isnan(value)) return "nan";
+    if (std::isinf(value)) return value < 0 ? "-inf" : "inf";
+    char buffer[64];
+    auto result = std::to_chars(buffer, buffer + sizeof buffer, value, std::chars_format::scientific);
+    std::string text(buffer, result.ptr);
+    int exponent = std::stoi(text.substr(text.find('e') + 1));
+    if (exponent < -4 || exponent >= 16) return text;
+    result = std::to_chars(buffer, buffer + sizeof buffer, value, std::chars_format::fixed);
+    text.assign(buffer, result.ptr);
+    if (text.find('.') == std::string::npos) text += ".0";
+    return text;
+}
+
+inline void cvadro_write_quoted(std::ostream& out, const std::string& text) {
+    char quote = text.find('\'') != std::string::npos && text.find('"') == std::string::npos ? '"' : '\'';
+    out << quote;
+    for (char c : text) {
+        switch (c) {
+            case '\\': out << "\\\\"; break;
+            case '\n': out << "\\n"; break;
+            case '\t': out << "\\t"; break;
+            case '\r': out << "\\r"; break;
+            default:
+                if (c == quote) out << '\\';
+                out << c;
+        }
+    }
+    out << quote;
+}
+
+// quoted: значение — элемент списка или поле структуры, строки пишутся в кавычках
+template<typename T>
+void cvadro_write(std::ostream& out, const T& value, bool quoted = false) {
+    if constexpr (std::is_same_v<T, bool>) {
+        out << (value ? "True" : "False");
+    } else if constexpr (std::is_same_v<T, char32_t>) {
+        if (quoted) {
+            std::ostringstream text;
+            text << value;
+            cvadro_write_quoted(out, text.str());
+        } else {
+            out << value;
+        }
+    } else if constexpr (std::is_floating_point_v<T>) {
+        out << cvadro_float_repr(value);
+    } else if constexpr (std::is_integral_v<T>) {
+        // Унарный плюс печатает i8 и u8 числом, а не символом
+        out << +value;
+    } else if constexpr (std::is_same_v<T, std::string>) {
+        if (quoted) cvadro_write_quoted(out, value);
+        else out << value;
+    } else if constexpr (std::is_same_v<T, std::nullopt_t>) {
+        out << "None";
+    } else if constexpr (cvadro_is_optional<T>::value) {
+        if (value) cvadro_write(out, *value, quoted);
+        else out << "None";
+    } else if constexpr (cvadro_is_list<T>::value) {
+        out << '[';
+        bool first = true;
+        for (const auto& item : value) {
+            if (!first) out << ", ";
+            first = false;
+            // Элемент std::vector<bool> — прокси-объект, приводим его к bool
+            cvadro_write(out, static_cast<const typename T::value_type&>(item), true);
+        }
+        out << ']';
+    } else if constexpr (cvadro_has_fields<T>::value) {
+        value.cvadro_write_fields(out);
+    } else if constexpr (std::is_function_v<T> || cvadro_is_function<T>::value) {
+        out << "<function>";
+    } else {
+        out << value;
+    }
+}
+
+// Вывод буферизуется: cout сбрасывается при выходе, перед сообщением об ошибке и перед вводом
+template<typename... T>
+void cvadro_print(const std::string& sep, const std::string& end, const T&... values) {
+    bool first = true;
+    ((std::cout << (first ? "" : sep), first = false, cvadro_write(std::cout, values)), ...);
+    std::cout << end;
+}
+
+template<typename... T>
+void print(const T&... values) {
+    cvadro_print(" ", "\n", values...);
+}
+
+"#;
+
+// Встроенные исключения. Место в исходном файле .cst запоминается при создании
+// исключения: перед каждой инструкцией программа отмечает текущую строку.
+const ERROR_RUNTIME: &str = r#"#include <exception>
+#include <type_traits>
+#include <utility>
+
+struct cvadro_location_t {
+    const char* file;
+    int line;
+};
+inline cvadro_location_t cvadro_location = {"", 0};
+#define CVADRO_AT(n) (cvadro_location = {cvadro_module_file, n})
+
+struct Error : std::exception {
+    std::string message;
+    cvadro_location_t location = cvadro_location;
+    Error(std::string message = "") : message(std::move(message)) {}
+    virtual const char* name() const { return "Error"; }
+    const char* what() const noexcept override { return message.c_str(); }
+};
+
+#define CVADRO_ERROR(Name) \
+    struct Name : Error { \
+        using Error::Error; \
+        const char* name() const override { return #Name; } \
+    };
+CVADRO_ERROR(ValueError)
+CVADRO_ERROR(IndexError)
+CVADRO_ERROR(KeyError)
+CVADRO_ERROR(TypeError)
+CVADRO_ERROR(ZeroDivisionError)
+CVADRO_ERROR(OverflowError)
+CVADRO_ERROR(IOError)
+CVADRO_ERROR(EOFError)
+CVADRO_ERROR(RuntimeError)
+#undef CVADRO_ERROR
+
+inline std::ostream& operator<<(std::ostream& out, const Error& error) {
+    return out << error.name() << ": " << error.message;
+}
+
+// Блок finally выполняется в деструкторе, в том числе при return. Исключение из try
+// перехватывается и сохраняется в error, поэтому деструктор никогда не работает во время
+// раскрутки стека: исключение из самого finally заменяет исходное, как в Python, а не
+// завершает программу через std::terminate.
+template<typename F>
+struct cvadro_finally {
+    F body;
+    std::exception_ptr error;
+    explicit cvadro_finally(F body) : body(std::move(body)) {}
+    ~cvadro_finally() noexcept(false) {
+        body();
+        if (error) std::rethrow_exception(error);
+    }
+};
+
+// Индекс строки считается в символах UTF-8: перегрузка для строк — в STRING_RUNTIME
+template<typename C, typename = std::enable_if_t<!std::is_same_v<std::decay_t<C>, std::string>>>
+decltype(auto) cvadro_index(C&& items, int64_t index) {
+    // Отрицательный индекс считается с конца, как в срезах: items[-1] — последний элемент
+    int64_t size = static_cast<int64_t>(items.size());
+    int64_t position = index < 0 ? size + index : index;
+    if (position < 0 || position >= size) {
+        throw IndexError("индекс " + std::to_string(index) + " вне диапазона -" + std::to_string(size) + ".." + std::to_string(size));
+    }
+    return items[position];
+}
+
+inline int cvadro_uncaught(const Error& error) {
+    std::cout.flush();
+    std::cerr << error.location.file << ":" << error.location.line
+              << ": необработанное исключение " << error << std::endl;
+    return 1;
+}
+
+inline int cvadro_uncaught(const std::exception& error) {
+    std::cout.flush();
+    std::cerr << cvadro_location.file << ":" << cvadro_location.line
+              << ": необработанное исключение: " << error.what() << std::endl;
+    return 1;
+}
+
+"#;
+
+// Арифметика с семантикой Python: // и % округляют частное вниз, а не к нулю,
+// поэтому остаток имеет знак делителя; ** над целыми остаётся целым
+// Целочисленные ветви constexpr, чтобы операции годились для const верхнего уровня
+const ARITHMETIC_RUNTIME: &str = r#"#include <cmath>
+#include <limits>
+#include <type_traits>
+
+// Наименьшее значение знакового типа, делённое на -1, не помещается в этот тип
+template<typename A, typename B>
+constexpr bool cvadro_is_min_by_minus_one(A a, B b) {
+    using R = decltype(a + b);
+    if constexpr (std::is_signed_v<R>) {
+        return static_cast<R>(a) == std::numeric_limits<R>::min() && static_cast<R>(b) == -1;
+    } else {
+        return false;
+    }
+}
+
+template<typename A, typename B>
+constexpr auto cvadro_floordiv(A a, B b) {
+    if (b == 0) {
+        throw ZeroDivisionError("целочисленное деление на ноль");
+    }
+    if constexpr (std::is_integral_v<A> && std::is_integral_v<B>) {
+        if (cvadro_is_min_by_minus_one(a, b)) {
+            throw OverflowError("переполнение при целочисленном делении");
+        }
+        decltype(a + b) quotient = a / b;
+        if (a % b != 0 && (a < 0) != (b < 0)) {
+            quotient -= 1;
+        }
+        return quotient;
+    } else {
+        return std::floor(a / b);
+    }
+}
+
+template<typename A, typename B>
+constexpr auto cvadro_mod(A a, B b) {
+    if (b == 0) {
+        throw ZeroDivisionError("деление на ноль при вычислении остатка");
+    }
+    if constexpr (std::is_integral_v<A> && std::is_integral_v<B>) {
+        // Остаток от деления наименьшего числа на -1 равен нулю, но в C++ это переполнение
+        if (cvadro_is_min_by_minus_one(a, b)) {
+            return decltype(a + b)(0);
+        }
+        decltype(a + b) remainder = a % b;
+        if (remainder != 0 && (remainder < 0) != (b < 0)) {
+            remainder += b;
+        }
+        return remainder;
+    } else {
+        decltype(a + b) remainder = std::fmod(a, b);
+        if (remainder != 0 && (remainder < 0) != (b < 0)) {
+            remainder += b;
+        }
+        return remainder;
+    }
+}
+
+template<typename A, typename B>
+constexpr auto cvadro_pow(A base, B exponent) {
+    if constexpr (std::is_integral_v<A> && std::is_integral_v<B>) {
+        if (exponent < 0) {
+            throw ValueError("отрицательная степень целого числа, используйте float");
+        }
+        // Тип результата — общий тип операндов, как у остальных операций
+        decltype(base + exponent) result = 1;
+        decltype(base + exponent) factor = base;
+        while (exponent > 0) {
+            if (exponent & 1) {
+                result *= factor;
+            }
+            exponent >>= 1;
+            if (exponent > 0) {
+                factor *= factor;
+            }
+        }
+        return result;
+    } else {
+        using R = decltype(base + exponent);
+        return static_cast<R>(std::pow(static_cast<double>(base), static_cast<double>(exponent)));
+    }
+}
+
+"#;
+
+// Арифметика в режиме --checked-arithmetic: +, -, *, /, **, сдвиги и унарный минус.
+// Переполнение целых чисел и целочисленное деление на ноль вызывают исключение
+// с местом в исходном файле вместо неопределённого поведения.
+const CHECKED_RUNTIME: &str = r#"#include <limits>
+#include <type_traits>
+
+template<typename A, typename B>
+constexpr auto cvadro_add(const A& a, const B& b) {
+    if constexpr (std::is_integral_v<A> && std::is_integral_v<B>) {
+        decltype(a + b) result{};
+        if (__builtin_add_overflow(a, b, &result)) {
+            throw OverflowError("переполнение при сложении");
+        }
+        return result;
+    } else {
+        return a + b;
+    }
+}
+
+template<typename A, typename B>
+constexpr auto cvadro_sub(const A& a, const B& b) {
+    if constexpr (std::is_integral_v<A> && std::is_integral_v<B>) {
+        decltype(a - b) result{};
+        if (__builtin_sub_overflow(a, b, &result)) {
+            throw OverflowError("переполнение при вычитании");
+        }
+        return result;
+    } else {
+        return a - b;
+    }
+}
+
+template<typename A, typename B>
+constexpr auto cvadro_mul(const A& a, const B& b) {
+    if constexpr (std::is_integral_v<A> && std::is_integral_v<B>) {
+        decltype(a * b) result{};
+        if (__builtin_mul_overflow(a, b, &result)) {
+            throw OverflowError("переполнение при умножении");
+        }
+        return result;
+    } else {
+        return a * b;
+    }
+}
+
+template<typename A, typename B>
+constexpr auto cvadro_checked_pow(A base, B exponent) {
+    if constexpr (std::is_integral_v<A> && std::is_integral_v<B>) {
+        if (exponent < 0) {
+            throw ValueError("отрицательная степень целого числа, используйте float");
+        }
+        decltype(base + exponent) result = 1;
+        decltype(base + exponent) factor = base;
+        while (exponent > 0) {
+            if ((exponent & 1) && __builtin_mul_overflow(result, factor, &result)) {
+                throw OverflowError("переполнение при возведении в степень");
+            }
+            exponent >>= 1;
+            // Квадрат множителя ещё войдёт в результат, поэтому его переполнение — тоже ошибка
+            if (exponent > 0 && __builtin_mul_overflow(factor, factor, &factor)) {
+                throw OverflowError("переполнение при возведении в степень");
+            }
+        }
+        return result;
+    } else {
+        using R = decltype(base + exponent);
+        return static_cast<R>(std::pow(static_cast<double>(base), static_cast<double>(exponent)));
+    }
+}
+
+template<typename A>
+constexpr auto cvadro_neg(const A& a) {
+    if constexpr (std::is_integral_v<A>) {
+        decltype(-a) result{};
+        if (__builtin_sub_overflow(0, a, &result)) {
+            throw OverflowError("переполнение при смене знака");
+        }
+        return result;
+    } else {
+        return -a;
+    }
+}
+
+// Сдвиг на ширину типа и больше в C++ — неопределённое поведение, а в Python — обычное число
+template<typename A, typename B>
+constexpr auto cvadro_shl(const A& a, const B& count) {
+    using R = decltype(a << count);
+    if (count < 0) {
+        throw ValueError("отрицательная величина сдвига");
+    }
+    R result{};
+    if (count >= std::numeric_limits<R>::digits) {
+        // Знаковый бит помещает только -1 << (ширина - 1), то есть наименьшее число
+        if constexpr (std::is_signed_v<R>) {
+            if (count == std::numeric_limits<R>::digits && a == -1) {
+                return std::numeric_limits<R>::min();
+            }
+        }
+        if (a != 0) {
+            throw OverflowError("переполнение при сдвиге влево");
+        }
+        return result;
+    }
+    // Сдвиг — умножение на 2**count, так переполнение проверяется и у отрицательных чисел
+    if (__builtin_mul_overflow(a, static_cast<R>(1) << count, &result)) {
+        throw OverflowError("переполнение при сдвиге влево");
+    }
+    return result;
+}
+
+template<typename A, typename B>
+constexpr auto cvadro_shr(const A& a, const B& count) {
+    using R = decltype(a >> count);
+    if (count < 0) {
+        throw ValueError("отрицательная величина сдвига");
+    }
+    if (count >= std::numeric_limits<R>::digits) {
+        return static_cast<R>(a < 0 ? -1 : 0);
+    }
+    return static_cast<R>(a >> count);
+}
+
+// x += y не расширяет тип x: результат должен в нём поместиться
+template<typename T, typename V>
+constexpr void cvadro_assign(T& target, const V& value) {
+    if constexpr (std::is_integral_v<T> && std::is_integral_v<V>) {
+        T narrowed = static_cast<T>(value);
+        if (static_cast<V>(narrowed) != value || (narrowed < T{}) != (value < V{})) {
+            throw OverflowError("результат не помещается в тип переменной");
+        }
+        target = narrowed;
+    } else {
+        target = value;
+    }
+}
+
+template<typename A, typename B>
+constexpr auto cvadro_div(const A& a, const B& b) {
+    if constexpr (std::is_integral_v<A> && std::is_integral_v<B>) {
+        using R = decltype(a / b);
+        if (b == 0) {
+            throw ZeroDivisionError("целочисленное деление на ноль");
+        }
+        if constexpr (std::is_signed_v<R>) {
+            if (static_cast<R>(a) == std::numeric_limits<R>::min() && static_cast<R>(b) == -1) {
+                throw OverflowError("переполнение при делении");
+            }
+        }
+        return a / b;
+    } else {
+        return a / b;
+    }
+}
+
+"#;
+
+// Явное приведение x as T. Как в Rust, дробное число при приведении к целому
+// отбрасывает дробную часть, NaN даёт 0, а значения вне диапазона — ближайшую границу.
+const CAST_RUNTIME: &str = r#"#include <limits>
+#include <type_traits>
+
+template<typename To, typename From>
+constexpr To cvadro_cast(From value) {
+    if constexpr (std::is_integral_v<To> && std::is_floating_point_v<From>) {
+        if (value != value) return 0;
+        if (value <= static_cast<From>(std::numeric_limits<To>::min())) return std::numeric_limits<To>::min();
+        if (value >= static_cast<From>(std::numeric_limits<To>::max())) return std::numeric_limits<To>::max();
+        return static_cast<To>(value);
+    } else {
+        return static_cast<To>(value);
+    }
+}
+
+"#;
+
+// Стандартный ввод: input(), read_line() и read_all(), а также разбор чисел int(s) и float(s).
+// Ошибки разбора — ValueError, как в Python.
+const INPUT_RUNTIME: &str = r#"#include <charconv>
+#include <cstdlib>
+#include <iterator>
+
+inline std::string cvadro_input(const std::string& prompt = "") {
+    std::cout << prompt;
+    std::cout.flush();
+    std::string line;
+    if (!std::getline(std::cin, line)) throw EOFError("ввод закончился");
+    if (!line.empty() && line.back() == '\r') line.pop_back();
+    return line;
+}
+
+inline std::optional<std::string> cvadro_read_line() {
+    std::string line;
+    if (!std::getline(std::cin, line)) return std::nullopt;
+    if (!line.empty() && line.back() == '\r') line.pop_back();
+    return line;
+}
+
+inline std::string cvadro_read_all() {
+    return std::string(std::istreambuf_iterator<char>(std::cin), std::istreambuf_iterator<char>());
+}
+
+// Число без пробелов по краям; знак + from_chars не принимает, его пропускаем здесь
+inline std::string cvadro_number_text(const std::string& text) {
+    const char* spaces = " \t\n\r\f\v";
+    size_t begin = text.find_first_not_of(spaces);
+    if (begin == std::string::npos) return "";
+    std::string number = text.substr(begin, text.find_last_not_of(spaces) - begin + 1);
+    if (number.size() > 1 && number[0] == '+' && number[1] != '-' && number[1] != '+') number.erase(0, 1);
+    return number;
+}
+
+inline int64_t cvadro_int(const std::string& text) {
+    std::string number = cvadro_number_text(text);
+    int64_t value = 0;
+    auto [end, error] = std::from_chars(number.data(), number.data() + number.size(), value);
+    if (error == std::errc::result_out_of_range) {
+        throw ValueError("число " + number + " не помещается в int");
+    }
+    if (number.empty() || error != std::errc() || end != number.data() + number.size()) {
+        throw ValueError("некорректное целое число: '" + text + "'");
+    }
+    return value;
+}
+
+inline double cvadro_float(const std::string& text) {
+    std::string number = cvadro_number_text(text);
+    double value = 0;
+    auto [end, error] = std::from_chars(number.data(), number.data() + number.size(), value);
+    if (number.empty() || end != number.data() + number.size()
+        || (error != std::errc() && error != std::errc::result_out_of_range)) {
+        throw ValueError("некорректное число: '" + text + "'");
+    }
+    // Слишком большое по модулю число становится бесконечностью, слишком малое — нулём
+    if (error == std::errc::result_out_of_range) value = std::strtod(number.c_str(), nullptr);
+    return value;
+}
+
+"#;
+
+// Стандартный модуль fs и open(): файлы и каталоги. Ошибки — исключение IOError.
+const FS_RUNTIME: &str = r#"#include <algorithm>
+#include <filesystem>
+#include <fstream>
+#include <sstream>
+#include <system_error>
+
+namespace fs {
+
+inline std::string read_file(const std::string& path) {
+    std::ifstream file(path, std::ios::binary);
+    if (!file) throw IOError("не удалось открыть файл " + path);
+    std::ostringstream text;
+    text << file.rdbuf();
+    return text.str();
+}
+
+inline void cvadro_save(const std::string& path, const std::string& text, std::ios::openmode mode) {
+    std::ofstream file(path, std::ios::binary | mode);
+    if (!file) throw IOError("не удалось открыть файл " + path + " для записи");
+    if (!(file << text).flush()) throw IOError("не удалось записать файл " + path);
+}
+
+inline void write_file(const std::string& path, const std::string& text) {
+    cvadro_save(path, text, std::ios::trunc);
+}
+
+inline void append_file(const std::string& path, const std::string& text) {
+    cvadro_save(path, text, std::ios::app);
+}
+
+inline bool exists(const std::string& path) {
+    std::error_code error;
+    return std::filesystem::exists(path, error);
+}
+
+// Имена элементов каталога по алфавиту, без . и ..
+inline std::vector<std::string> list_dir(const std::string& path) {
+    std::error_code error;
+    std::vector<std::string> names;
+    for (std::filesystem::directory_iterator it(path, error), end; !error && it != end; it.increment(error)) {
+        names.push_back(it->path().filename().string());
+    }
+    if (error) throw IOError("не удалось прочитать каталог " + path + ": " + error.message());
+    std::sort(names.begin(), names.end());
+    return names;
+}
+
+// Удаляет файл или пустой каталог
+inline void remove(const std::string& path) {
+    std::error_code error;
+    if (!std::filesystem::remove(path, error)) {
+        throw IOError(error ? "не удалось удалить " + path + ": " + error.message() : path + " не существует");
+    }
+}
+
+// Создаёт каталог вместе с недостающими родительскими; существующий каталог — не ошибка
+inline void mkdir(const std::string& path) {
+    std::error_code error;
+    std::filesystem::create_directories(path, error);
+    if (error) throw IOError("не удалось создать каталог " + path + ": " + error.message());
+}
+
+} // namespace fs
+
+// for line in open(path) читает файл по строке, не загружая его целиком
+struct cvadro_file_lines {
+    std::ifstream file;
+
+    explicit cvadro_file_lines(const std::string& path) : file(path, std::ios::binary) {
+        if (!file) throw IOError("не удалось открыть файл " + path);
+    }
+
+    struct iterator {
+        std::ifstream* file;
+        std::string line;
+        iterator& operator++() {
+            if (!std::getline(*file, line)) file = nullptr;
+            else if (!line.empty() && line.back() == '\r') line.pop_back();
+            return *this;
+        }
+        const std::string& operator*() const { return line; }
+        bool operator!=(const iterator& other) const { return file != other.file; }
+    };
+
+    iterator begin() { return ++iterator{&file, ""}; }
+    iterator end() { return {nullptr, ""}; }
+};
+
+inline std::vector<std::string> cvadro_open(const std::string& path) {
+    std::vector<std::string> lines;
+    for (const auto& line : cvadro_file_lines(path)) lines.push_back(line);
+    return lines;
+}
+
+"#;
+
+// Форматирование значения для f-строк по спецификации в стиле Python
+const FORMAT_RUNTIME: &str = r#"#include <sstream>
+#include <iomanip>
+#include <cmath>
+#include <type_traits>
+
+// Длина строки в символах UTF-8, а не в байтах
+inline size_t cvadro_utf8_len(const std::string& text) {
+    size_t count = 0;
+    for (unsigned char c : text) {
+        if ((c & 0xC0) != 0x80) count++;
+    }
+    return count;
+}
+
+template<typename T>
+std::string cvadro_format(const T& value, const char* fill = " ", char align = 0, char sign = '-',
+                          size_t width = 0, int precision = -1, char type = 0) {
+    std::ostringstream out;
+    bool numeric = false;
+    if constexpr (std::is_same<T, bool>::value) {
+        out << (value ? "True" : "False");
+    } else if constexpr (std::is_same<T, char32_t>::value) {
+        out << value;
+    } else if constexpr (std::is_arithmetic<T>::value) {
+        numeric = true;
+        bool negative = value < 0;
+        bool integer_type = type == 'b' || type == 'd' || type == 'o' || type == 'x' || type == 'X';
+        std::ostringstream number;
+        if (integer_type || (type == 0 && std::is_integral<T>::value)) {
+            long long n = static_cast<long long>(value);
+            unsigned long long magnitude = negative ? 0ULL - static_cast<unsigned long long>(n) : n;
+            if (type == 'b') {
+                std::string digits;
+                do { digits.insert(digits.begin(), char('0' + (magnitude & 1))); magnitude >>= 1; } while (magnitude);
+                number << digits;
+            } else {
+                if (type == 'o') number << std::oct;
+                if (type == 'x' || type == 'X') number << std::hex;
+                if (type == 'X') number << std::uppercase;
+                number << magnitude;
+            }
+        } else {
+            double d = std::fabs(static_cast<double>(value));
+            if (type == '%') d *= 100;
+            if (type == 'f' || type == 'F' || type == '%') number << std::fixed;
+            if (type == 'e' || type == 'E') number << std::scientific;
+            if (type == 'E' || type == 'F' || type == 'G') number << std::uppercase;
+            if (precision >= 0) number << std::setprecision(precision);
+            // Без типа и точности число записывается как в print
+            if (type == 0 && precision < 0) number << cvadro_float_repr(std::fabs(value));
+            else number << d;
+            if (type == '%') number << '%';
+        }
+        out << (negative ? "-" : sign == '+' ? "+" : sign == ' ' ? " " : "") << number.str();
+    } else if constexpr (std::is_convertible<T, std::string>::value) {
+        std::string text = value;
+        if (precision >= 0) {
+            // Обрезаем по символам UTF-8, не разрывая многобайтовые последовательности
+            size_t chars = 0, i = 0;
+            for (; i < text.size(); i++) {
+                if ((static_cast<unsigned char>(text[i]) & 0xC0) != 0x80 && chars++ == static_cast<size_t>(precision)) break;
+            }
+            text.resize(i);
+        }
+        out << text;
+    } else {
+        cvadro_write(out, value);
+    }
+
+    std::string body = out.str();
+    size_t length = cvadro_utf8_len(body);
+    if (width <= length) return body;
+
+    size_t padding = width - length;
+    auto pad = [&](size_t count) {
+        std::string result;
+        for (size_t i = 0; i < count; i++) result += fill;
+        return result;
+    };
+    char how = align ? align : (numeric ? '>' : '<');
+    switch (how) {
+        case '<': return body + pad(padding);
+        case '^': return pad(padding / 2) + body + pad(padding - padding / 2);
+        case '=':
+            if (!body.empty() && (body[0] == '-' || body[0] == '+' || body[0] == ' ')) {
+                return body.substr(0, 1) + pad(padding) + body.substr(1);
+            }
+            return pad(padding) + body;
+        default: return pad(padding) + body;
+    }
+}
+
+"#;
+
+// Строки хранятся в UTF-8. Длина, индексы, срезы и find считаются в символах, а не в байтах,
+// for по строке перебирает символы. Методы строк вызываются через cvadro_method_*: генератор
+// кода не знает типов, поэтому у строки вызывается функция из cvadro_string, а у структуры —
+// её поле-функция с тем же именем.
+const STRING_RUNTIME: &str = r#"#include <algorithm>
+#include <optional>
+
+namespace cvadro_string {
+
+inline bool is_continuation(unsigned char c) {
+    return (c & 0xC0) == 0x80;
+}
+
+inline int64_t len(const std::string& text) {
+    int64_t count = 0;
+    for (unsigned char c : text) {
+        if (!is_continuation(c)) count++;
+    }
+    return count;
+}
+
+// Смещение в байтах символа с номером index; за последним символом — размер строки
+inline size_t offset(const std::string& text, int64_t index) {
+    size_t i = 0;
+    for (; i < text.size(); i++) {
+        if (!is_continuation(text[i]) && index-- == 0) break;
+    }
+    return i;
+}
+
+inline std::u32string decode(const std::string& text) {
+    std::u32string result;
+    for (size_t i = 0; i < text.size();) {
+        unsigned char lead = text[i++];
+        int count = lead >= 0xF0 ? 3 : lead >= 0xE0 ? 2 : lead >= 0xC0 ? 1 : 0;
+        char32_t code = count ? lead & (0x3F >> count) : lead;
+        for (; count > 0 && i < text.size(); count--, i++) {
+            code = (code << 6) | (text[i] & 0x3F);
+        }
+        result += code;
+    }
+    return result;
+}
+
+inline std::string encode(const std::u32string& codes) {
+    std::string result;
+    for (char32_t code : codes) {
+        if (code < 0x80) {
+            result += static_cast<char>(code);
+        } else if (code < 0x800) {
+            result += static_cast<char>(0xC0 | (code >> 6));
+            result += static_cast<char>(0x80 | (code & 0x3F));
+        } else if (code < 0x10000) {
+            result += static_cast<char>(0xE0 | (code >> 12));
+            result += static_cast<char>(0x80 | ((code >> 6) & 0x3F));
+            result += static_cast<char>(0x80 | (code & 0x3F));
+        } else {
+            result += static_cast<char>(0xF0 | (code >> 18));
+            result += static_cast<char>(0x80 | ((code >> 12) & 0x3F));
+            result += static_cast<char>(0x80 | ((code >> 6) & 0x3F));
+            result += static_cast<char>(0x80 | (code & 0x3F));
+        }
+    }
+    return result;
+}
+
+// Регистр меняется у латиницы, Latin-1, греческих букв и основной кириллицы
+inline char32_t to_upper(char32_t c) {
+    if (c >= U'a' && c <= U'z') return c - 32;
+    if (c >= 0xE0 && c <= 0xFE && c != 0xF7) return c - 32;
+    if (c >= 0x3B1 && c <= 0x3C9 && c != 0x3C2) return c - 32;
+    if (c >= 0x430 && c <= 0x44F) return c - 32;
+    if (c >= 0x450 && c <= 0x45F) return c - 80;
+    return c;
+}
+
+inline char32_t to_lower(char32_t c) {
+    if (c >= U'A' && c <= U'Z') return c + 32;
+    if (c >= 0xC0 && c <= 0xDE && c != 0xD7) return c + 32;
+    if (c >= 0x391 && c <= 0x3A9 && c != 0x3A2) return c + 32;
+    if (c >= 0x410 && c <= 0x42F) return c + 32;
+    if (c >= 0x400 && c <= 0x40F) return c + 80;
+    return c;
+}
+
+inline std::string upper(const std::string& text) {
+    std::u32string codes = decode(text);
+    std::transform(codes.begin(), codes.end(), codes.begin(), to_upper);
+    return encode(codes);
+}
+
+inline std::string lower(const std::string& text) {
+    std::u32string codes = decode(text);
+    std::transform(codes.begin(), codes.end(), codes.begin(), to_lower);
+    return encode(codes);
+}
+
+inline bool is_space(char c) {
+    return c == ' ' || c == '\t' || c == '\n' || c == '\r' || c == '\f' || c == '\v';
+}
+
+inline std::string strip(const std::string& text) {
+    size_t begin = 0, end = text.size();
+    while (begin < end && is_space(text[begin])) begin++;
+    while (end > begin && is_space(text[end - 1])) end--;
+    return text.substr(begin, end - begin);
+}
+
+// Без разделителя, как в Python, строка делится по пробельным символам, а пустые части отбрасываются
+inline std::vector<std::string> split(const std::string& text) {
+    std::vector<std::string> parts;
+    size_t i = 0;
+    while (i < text.size()) {
+        while (i < text.size() && is_space(text[i])) i++;
+        size_t begin = i;
+        while (i < text.size() && !is_space(text[i])) i++;
+        if (i > begin) parts.push_back(text.substr(begin, i - begin));
+    }
+    return parts;
+}
+
+inline std::vector<std::string> split(const std::string& text, const std::string& separator) {
+    if (separator.empty()) {
+        throw ValueError("пустой разделитель");
+    }
+    std::vector<std::string> parts;
+    size_t begin = 0, found;
+    while ((found = text.find(separator, begin)) != std::string::npos) {
+        parts.push_back(text.substr(begin, found - begin));
+        begin = found + separator.size();
+    }
+    parts.push_back(text.substr(begin));
+    return parts;
+}
+
+inline std::string join(const std::string& separator, const std::vector<std::string>& items) {
+    std::string result;
+    for (size_t i = 0; i < items.size(); i++) {
+        if (i > 0) result += separator;
+        result += items[i];
+    }
+    return result;
+}
+
+inline std::string replace(const std::string& text, const std::string& old, const std::string& replacement) {
+    std::string result;
+    if (old.empty()) {
+        // Как в Python: замена вставляется перед каждым символом и в конце
+        for (size_t i = 0; i < text.size(); i++) {
+            if (!is_continuation(text[i])) result += replacement;
+            result += text[i];
+        }
+        return result + replacement;
+    }
+    size_t begin = 0, found;
+    while ((found = text.find(old, begin)) != std::string::npos) {
+        result += text.substr(begin, found - begin) + replacement;
+        begin = found + old.size();
+    }
+    return result + text.substr(begin);
+}
+
+inline int64_t find(const std::string& text, const std::string& part) {
+    size_t found = text.find(part);
+    return found == std::string::npos ? -1 : len(text.substr(0, found));
+}
+
+inline bool startswith(const std::string& text, const std::string& prefix) {
+    return text.compare(0, prefix.size(), prefix) == 0;
+}
+
+inline bool endswith(const std::string& text, const std::string& suffix) {
+    return text.size() >= suffix.size() && text.compare(text.size() - suffix.size(), suffix.size(), suffix) == 0;
+}
+
+// Границы среза как в Python: отрицательные считаются с конца, выходящие за пределы обрезаются
+inline std::pair<int64_t, int64_t> slice_bounds(int64_t size, std::optional<int64_t> start, std::optional<int64_t> end) {
+    auto clamp = [size](int64_t index) {
+        if (index < 0) index += size;
+        return std::clamp<int64_t>(index, 0, size);
+    };
+    int64_t from = start ? clamp(*start) : 0;
+    int64_t to = end ? clamp(*end) : size;
+    return {from, std::max(from, to)};
+}
+
+} // namespace cvadro_string
+
+#define CVADRO_STRING_METHOD(method) \
+    template<typename S, typename... A> \
+    decltype(auto) cvadro_method_##method(S&& object, A&&... args) { \
+        if constexpr (std::is_same_v<std::decay_t<S>, std::string>) { \
+            return cvadro_string::method(object, std::forward<A>(args)...); \
+        } else { \
+            return object.method(std::forward<A>(args)...); \
+        } \
+    }
+CVADRO_STRING_METHOD(upper)
+CVADRO_STRING_METHOD(lower)
+CVADRO_STRING_METHOD(strip)
+CVADRO_STRING_METHOD(split)
+CVADRO_STRING_METHOD(join)
+CVADRO_STRING_METHOD(replace)
+CVADRO_STRING_METHOD(find)
+CVADRO_STRING_METHOD(startswith)
+CVADRO_STRING_METHOD(endswith)
+#undef CVADRO_STRING_METHOD
+
+// len() есть и у списков
+template<typename S>
+decltype(auto) cvadro_method_len(S&& object) {
+    if constexpr (std::is_same_v<std::decay_t<S>, std::string>) {
+        return cvadro_string::len(object);
+    } else if constexpr (cvadro_is_list<std::decay_t<S>>::value) {
+        return static_cast<int64_t>(object.size());
+    } else {
+        return object.len();
+    }
+}
+
+inline std::string cvadro_index(const std::string& text, int64_t index) {
+    int64_t size = cvadro_string::len(text);
+    int64_t position = index < 0 ? size + index : index;
+    if (position < 0 || position >= size) {
+        throw IndexError("индекс " + std::to_string(index) + " вне диапазона -" + std::to_string(size) + ".." + std::to_string(size));
+    }
+    size_t begin = cvadro_string::offset(text, position);
+    return text.substr(begin, cvadro_string::offset(text, position + 1) - begin);
+}
+
+inline std::string cvadro_slice(const std::string& text, std::optional<int64_t> start, std::optional<int64_t> end) {
+    auto [from, to] = cvadro_string::slice_bounds(cvadro_string::len(text), start, end);
+    size_t begin = cvadro_string::offset(text, from);
+    return text.substr(begin, cvadro_string::offset(text, to) - begin);
+}
+
+template<typename T>
+std::vector<T> cvadro_slice(const std::vector<T>& items, std::optional<int64_t> start, std::optional<int64_t> end) {
+    auto [from, to] = cvadro_string::slice_bounds(items.size(), start, end);
+    return std::vector<T>(items.begin() + from, items.begin() + to);
+}
+
+inline bool cvadro_contains(const std::string& text, const std::string& part) {
+    return text.find(part) != std::string::npos;
+}
+
+template<typename T, typename V>
+bool cvadro_contains(const std::vector<T>& items, const V& item) {
+    return std::find(items.begin(), items.end(), item) != items.end();
+}
+
+inline std::vector<std::string> cvadro_iter(const std::string& text) {
+    std::vector<std::string> chars;
+    for (size_t i = 0; i < text.size(); i++) {
+        if (!cvadro_string::is_continuation(text[i])) chars.emplace_back();
+        chars.back() += text[i];
+    }
+    return chars;
+}
+
+// Список перебирается без копирования, а временный — перемещается, чтобы пережить цикл
+template<typename C, typename = std::enable_if_t<!std::is_same_v<std::decay_t<C>, std::string>>>
+C cvadro_iter(C&& items) {
+    return std::forward<C>(items);
+}
+
+"#;
+
+// Встроенные функции ввода и open. В C++ у них префикс, чтобы не столкнуться с функциями программы,
+// а int(s) и float(s) там к тому же — приведения типов.
+const BUILTIN_FUNCTIONS: &[(&str, &str)] = &[
+    ("input", "cvadro_input"),
+    ("read_line", "cvadro_read_line"),
+    ("read_all", "cvadro_read_all"),
+    ("int", "cvadro_int"),
+    ("float", "cvadro_float"),
+    ("open", "cvadro_open"),
+];
+
+// Методы строк, которые вызываются через cvadro_method_*
+const STRING_METHODS: &[&str] = &[
+    "len", "upper", "lower", "strip", "split", "join", "replace", "find", "startswith", "endswith",
+];
+
+// Среда выполнения для pyimport: каждый вызов запускает мост imports/_import_.py,
+// который импортирует модуль Python и вызывает функцию. Аргументы передаются
+// в командной строке с метками типов, строки — в шестнадцатеричном виде, чтобы
+// не зависеть от правил экранирования оболочки.
+const PYTHON_RUNTIME: &str = r#"#include <cstdio>
+#include <cstdlib>
+#include <vector>
+
+namespace cvadro_py {
+
+inline std::string hex(const std::string& text) {
+    static const char* digits = "0123456789abcdef";
+    std::string out;
+    for (unsigned char c : text) {
+        out += digits[c >> 4];
+        out += digits[c & 15];
+    }
+    return out;
+}
+
+template<typename T, typename = std::enable_if_t<std::is_integral_v<T> && !std::is_same_v<T, bool>>>
+inline std::string arg(T value) { return "i:" + std::to_string(value); }
+inline std::string arg(double value) {
+    char buffer[32];
+    std::snprintf(buffer, sizeof buffer, "%.17g", value);
+    return std::string("f:") + buffer;
+}
+inline std::string arg(bool value) { return value ? "b:1" : "b:0"; }
+inline std::string arg(const std::string& value) { return "s:" + hex(value); }
+inline std::string arg(const char* value) { return arg(std::string(value)); }
+
+// Путь для командной строки. В POSIX — в одинарных кавычках, внутри которых оболочка
+// не раскрывает $, ` и прочее; сама кавычка записывается как '\''. В Windows пути
+// не могут содержать ", поэтому достаточно двойных кавычек.
+inline std::string quote(const std::string& text) {
+#ifdef _WIN32
+    return "\"" + text + "\"";
+#else
+    std::string out = "'";
+    for (char c : text) {
+        if (c == '\'') out += "'\\''";
+        else out += c;
+    }
+    return out + "'";
+#endif
+}
+
+inline std::string run(const std::string& module_dir, const char* module, const char* function,
+                       const std::vector<std::string>& args) {
+    const char* bridge = std::getenv("CVADROSCRIPT_PY_BRIDGE");
+    const char* python = std::getenv("CVADROSCRIPT_PYTHON");
+#ifdef _WIN32
+    if (!python) python = "python";
+#else
+    if (!python) python = "python3";
+#endif
+    if (!bridge) {
+        std::cerr << "Ошибка: не найден мост Python (CVADROSCRIPT_PY_BRIDGE)" << std::endl;
+        std::exit(1);
+    }
+
+    // Имена модуля и функции — идентификаторы, аргументы и каталог — в hex: кавычки им не нужны
+    std::string command = quote(python) + " " + quote(bridge) + " " + hex(module_dir) + " " + module + " " + function;
+    for (const auto& a : args) {
+        command += " " + a;
+    }
+#ifdef _WIN32
+    FILE* pipe = _popen(("\"" + command + "\"").c_str(), "rb");
+#else
+    FILE* pipe = popen(command.c_str(), "r");
+#endif
+    if (!pipe) {
+        std::cerr << "Ошибка: не удалось запустить " << python << std::endl;
+        std::exit(1);
+    }
+
+    std::string output;
+    char buffer[4096];
+    size_t count;
+    while ((count = std::fread(buffer, 1, sizeof buffer, pipe)) > 0) {
+        output.append(buffer, count);
+    }
+#ifdef _WIN32
+    int status = _pclose(pipe);
+#else
+    int status = pclose(pipe);
+#endif
+    if (status != 0) {
+        throw RuntimeError(std::string("ошибка при вызове функции Python ") + module + "." + function);
+    }
+    return output;
+}
+
+// Разбор результата моста; false, если функция вернула значение другого типа
+template<typename T>
+bool convert(const std::string& text, T& value) {
+    auto [end, error] = std::from_chars(text.data(), text.data() + text.size(), value);
+    return !text.empty() && error == std::errc() && end == text.data() + text.size();
 }
+inline bool convert(const std::string& text, bool& value) {
+    value = text == "1";
+    return text == "1" || text == "0";
+}
+inline bool convert(const std::string& text, std::string& value) {
+    value = text;
+    return true;
+}
+
+template<typename T> const char* type_name();
+template<> inline const char* type_name<int64_t>() { return "int"; }
+template<> inline const char* type_name<double>() { return "float"; }
+template<> inline const char* type_name<bool>() { return "bool"; }
+template<> inline const char* type_name<std::string>() { return "string"; }
+
+template<typename T>
+T call(const std::string& module_dir, const char* module, const char* function,
+       const std::vector<std::string>& args) {
+    std::string result = run(module_dir, module, function, args);
+    if constexpr (!std::is_void_v<T>) {
+        T value{};
+        if (!convert(result, value)) {
+            // None мост передаёт пустой строкой
+            throw TypeError(std::string("функция Python ") + module + "." + function + " вернула "
+                            + (result.empty() ? "None" : "«" + result + "»") + ", а ожидался " + type_name<T>());
+        }
+        return value;
+    }
+}
+
+} // namespace cvadro_py
+
+"#;
 
 impl CodeGen {
-    pub fn generate(ast: Vec<Stmt>) -> String {
+    pub fn generate(modules: &[Module], checked_arithmetic: bool) -> String {
         let mut gen = CodeGen {
             output: String::new(),
             indent_level: 0,
+            imported: Vec::new(),
+            signatures: HashMap::new(),
+            module_signatures: HashMap::new(),
+            module_dir: String::new(),
+            runtime: Vec::new(),
+            temp_counter: 0,
+            in_entry: false,
+            checked_arithmetic,
         };
-        gen.gen_program(ast);
+        
+        for name in STD_MODULES {
+            let exports = std_module(name).unwrap_or_default().iter()
+                .map(|f| (f.name.clone(), function_signature(f)))
+                .collect();
+            gen.module_signatures.insert(name.to_string(), exports);
+        }
+        
+        // Главный модуль загружается последним, остальные уже упорядочены по зависимостям
+        if let Some((entry, dependencies)) = modules.split_last() {
+            for module in dependencies {
+                gen.gen_module(module);
+            }
+            gen.enter_module(entry);
+            gen.in_entry = true;
+            gen.gen_module_file(entry);
+            gen.gen_program(&entry.ast);
+        }
+        
+        // Пролог и нужные части среды выполнения ставим перед сгенерированным кодом
+        let program = std::mem::take(&mut gen.output);
+        gen.gen_prelude();
+        for snippet in &gen.runtime {
+            gen.output.push_str(snippet);
+        }
+        gen.output.push_str(&program);
         gen.output
     }
     
-    fn gen_program(&mut self, stmts: Vec<Stmt>) {
+    fn require(&mut self, snippet: &'static str) {
+        if !self.runtime.contains(&snippet) {
+            self.runtime.push(snippet);
+        }
+    }
+    
+    fn gen_prelude(&mut self) {
         self.output.push_str("#include <iostream>\n");
-        self.output.push_str("#include <string>\n\n");
-        self.output.push_str("// Вспомогательная функция print\n");
-        self.output.push_str("template<typename T>\n");
-        self.output.push_str("void print(T value) {\n");
-        self.output.push_str("    std::cout << value << std::endl;\n");
-        self.output.push_str("}\n\n");
-        
-        let mut has_main = false;
+        self.output.push_str("#include <string>\n");
+        self.output.push_str("#include <vector>\n");
+        self.output.push_str("#include <functional>\n");
+        self.output.push_str("#include <optional>\n");
+        self.output.push_str("#include <cstdint>\n");
+        self.output.push_str("#include <limits>\n\n");
+        self.output.push_str(STREAM_RUNTIME);
+        self.output.push_str(PRINT_RUNTIME);
+        self.output.push_str(ERROR_RUNTIME);
+        self.output.push_str(INPUT_RUNTIME);
+    }
+    
+    // Импортируемый модуль целиком помещается в одноимённое пространство имён
+    fn enter_module(&mut self, module: &Module) {
+        self.imported = module.ast.iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::Import(name) | Stmt::FromImport(name, _) | Stmt::PyImport(name, _) => Some(name.clone()),
+                _ => None,
+            })
+            .collect();
+        self.signatures = local_signatures(&module.ast);
+        for stmt in &module.ast {
+            if let Stmt::Import(imported) | Stmt::FromImport(imported, _) = stmt {
+                if let Some(snippet) = std_runtime(imported) {
+                    self.require(snippet);
+                }
+            }
+            match stmt {
+                Stmt::FromImport(imported, names) => {
+                    for name in names {
+                        let signature = self.module_signatures.get(imported).and_then(|exports| exports.get(name));
+                        if let Some(signature) = signature.cloned() {
+                            self.signatures.insert(name.clone(), signature);
+                        }
+                    }
+                },
+                Stmt::PyImport(imported, functions) => {
+                    let exports = functions.iter()
+                        .map(|f| (f.name.clone(), function_signature(f)))
+                        .collect();
+                    self.module_signatures.insert(imported.clone(), exports);
+                },
+                _ => {}
+            }
+        }
+        self.module_dir = module.path.parent()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_default();
+    }
+    
+    fn gen_module(&mut self, module: &Module) {
+        self.enter_module(module);
+        self.output.push_str(&format!("namespace {} {{\n\n", module.name));
+        self.gen_module_file(module);
+        self.gen_declarations(&module.ast);
+        self.output.push_str(&format!("}} // namespace {}\n\n", module.name));
+        
+        self.module_signatures.insert(module.name.clone(), local_signatures(&module.ast));
+    }
+    
+    // Имя файла модуля для сообщений об исключениях
+    fn gen_module_file(&mut self, module: &Module) {
+        let file = module.path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| module.name.clone());
+        self.push_line(&format!("constexpr const char* cvadro_module_file = {};", cpp_string(&file)));
+        self.push_line("");
+    }
+    
+    // Генерирует импорты, структуры и функции; возвращает остальные инструкции
+    fn gen_declarations(&mut self, stmts: &[Stmt]) -> Vec<Stmt> {
+        let mut imports = Vec::new();
+        let mut structs = Vec::new();
+        let mut globals = Vec::new();
         let mut functions = Vec::new();
         let mut global_stmts = Vec::new();
+        let mut line = None;
         
-        // Разделяем функции и глобальные выражения
+        // Разделяем импорты, структуры, функции и глобальные выражения.
+        // Отметка строки остаётся только перед глобальными выражениями.
         for stmt in stmts {
             match stmt {
-                Stmt::Function(ref func) if func.name == "main" => {
-                    has_main = true;
-                    functions.push(stmt);
+                Stmt::Line(_) => {
+                    line = Some(stmt);
+                },
+                Stmt::Import(_) | Stmt::FromImport(..) | Stmt::PyImport(..) => {
+                    imports.push(stmt);
                 },
-                Stmt::Function(_) => {
-                    functions.push(stmt);
+                Stmt::Struct(def) => {
+                    structs.push(def);
+                },
+                Stmt::Function(f) => {
+                    functions.push(f);
+                },
+                // Переменные с известным типом становятся глобальными, чтобы их видели функции.
+                // Литерал подставляется в объявление, остальные значения вычисляются в main по порядку.
+                Stmt::Let(name, Some(_), expr) | Stmt::Const(name, Some(_), expr) => {
+                    globals.push(stmt);
+                    if !expr.is_literal() && matches!(stmt, Stmt::Let(..)) {
+                        global_stmts.extend(line.take().cloned());
+                        global_stmts.push(Stmt::Assign(Expr::Variable(name.clone()), expr.clone()));
+                    }
                 },
                 _ => {
-                    global_stmts.push(stmt);
+                    global_stmts.extend(line.take().cloned());
+                    global_stmts.push(stmt.clone());
                 }
             }
         }
         
+        for stmt in imports {
+            self.gen_stmt(stmt);
+        }
+        
+        // Структуры объявляются раньше функций, которые их используют, а каждая
+        // структура — после тех, что входят в неё по значению
+        for def in order_structs(&structs) {
+            self.gen_struct(def);
+        }
+        
+        if !globals.is_empty() {
+            for stmt in globals {
+                self.gen_global(stmt);
+            }
+            self.push_line("");
+        }
+        
+        // Прототипы позволяют вызывать функции до их определения и из взаимной рекурсии.
+        // Значения параметров по умолчанию указываются только в прототипе.
+        for f in &functions {
+            let return_type = self.type_to_cpp(&f.return_type);
+            let params = self.gen_params(&f.params, true);
+            self.gen_template_header(&f.type_params);
+            self.push_line(&format!("{} {}({});", return_type, self.cpp_name(&f.name), params));
+        }
+        if !functions.is_empty() {
+            self.push_line("");
+        }
+        
         // Генерируем функции
-        for stmt in functions {
-            self.gen_stmt(&stmt);
+        for f in functions {
+            self.gen_function(f);
         }
         
-        // Если нет функции main, создаем её и добавляем туда глобальные выражения
-        if !has_main {
-            self.output.push_str("int main() {\n");
-            self.indent_level += 1;
-            
-            for stmt in global_stmts {
-                self.gen_stmt(&stmt);
+        global_stmts
+    }
+    
+    // Точка входа C++: сначала выполняются инструкции верхнего уровня, затем main
+    // пользователя, если она есть. Её результат становится кодом возврата программы.
+    // Необработанное исключение печатает место в файле .cst и завершает программу с кодом 1.
+    fn gen_program(&mut self, stmts: &[Stmt]) {
+        let user_main = stmts.iter().find_map(|stmt| match stmt {
+            Stmt::Function(func) if func.name == "main" => Some(func.clone()),
+            _ => None,
+        });
+        let global_stmts = self.gen_declarations(stmts);
+        
+        self.output.push_str("int main(int argc, char** argv) {\n");
+        self.indent_level += 1;
+        if user_main.as_ref().is_some_and(|main| !main.params.is_empty()) {
+            self.push_line("std::vector<std::string> cvadro_args(argv + 1, argv + argc);");
+        }
+        self.push_line("try {");
+        self.indent_level += 1;
+        
+        self.gen_stmts(&global_stmts);
+        if let Some(main) = &user_main {
+            let args = if main.params.is_empty() { "" } else { "cvadro_args" };
+            if main.return_type == Type::Int {
+                self.push_line(&format!("return cvadro_main({});", args));
+            } else {
+                self.push_line(&format!("cvadro_main({});", args));
             }
-            
-            self.indent_level -= 1;
-            self.output.push_str("    return 0;\n");
-            self.output.push_str("}\n");
         }
+        
+        self.indent_level -= 1;
+        self.push_line("} catch (const Error& error) {");
+        self.push_line("    return cvadro_uncaught(error);");
+        self.push_line("} catch (const std::exception& error) {");
+        self.push_line("    return cvadro_uncaught(error);");
+        self.push_line("}");
+        self.indent_level -= 1;
+        self.output.push_str("    return 0;\n");
+        self.output.push_str("}\n");
     }
     
     fn gen_stmt(&mut self, stmt: &Stmt) {
@@ -70,16 +1445,62 @@ impl CodeGen {
                 let expr_code = self.gen_expr(expr);
                 self.push_line(&format!("{};", expr_code));
             },
-            Stmt::Let(name, _type, expr) => {
+            Stmt::Let(name, type_annotation, expr) | Stmt::Const(name, type_annotation, expr) => {
                 let expr_code = self.gen_expr(expr);
-                self.push_line(&format!("auto {} = {};", name, expr_code));
+                let type_code = match type_annotation {
+                    Some(ty) => self.type_to_cpp(ty),
+                    None => "auto".to_string(),
+                };
+                self.push_line(&format!("{} {} = {};", type_code, name, expr_code));
             },
-            Stmt::Assign(name, expr) => {
+            Stmt::Assign(target, expr) => {
+                let target_code = self.gen_expr(target);
                 let expr_code = self.gen_expr(expr);
-                self.push_line(&format!("{} = {};", name, expr_code));
+                self.push_line(&format!("{} = {};", target_code, expr_code));
+            },
+            Stmt::AugAssign(target, op, expr) => {
+                let target_code = self.gen_expr(target);
+                let expr_code = self.gen_operand(expr, target);
+                if let Some(helper) = self.arithmetic_helper(op) {
+                    // В режиме --checked-arithmetic результат должен поместиться в тип x
+                    let store = |place: &str, value: String| if self.checked_arithmetic {
+                        format!("cvadro_assign({}, {});", place, value)
+                    } else {
+                        format!("{} = {};", place, value)
+                    };
+                    // Место присваивания вычисляется один раз: a[f()] //= 2
+                    let line = match target {
+                        Expr::Variable(_) => store(&target_code, format!("{}({}, {})", helper, target_code, expr_code)),
+                        _ => {
+                            self.temp_counter += 1;
+                            let place = format!("cvadro_target_{}", self.temp_counter);
+                            let value = format!("{}({}, {})", helper, place, expr_code);
+                            format!("{{ auto& {} = {}; {} }}", place, target_code, store(&place, value))
+                        }
+                    };
+                    if self.checked_arithmetic {
+                        self.require(CHECKED_RUNTIME);
+                    }
+                    self.push_line(&line);
+                    return;
+                }
+                let op_str = self.gen_binop(op);
+                self.push_line(&format!("{} {}= {};", target_code, op_str, expr_code));
             },
             Stmt::Function(func) => {
-                self.gen_function(func);
+                self.gen_nested_function(func);
+            },
+            Stmt::Struct(def) => {
+                self.gen_struct(def);
+            },
+            Stmt::Import(_) => {},
+            Stmt::FromImport(module, names) => {
+                for name in names {
+                    self.push_line(&format!("using {}::{};", module, name));
+                }
+            },
+            Stmt::PyImport(module, functions) => {
+                self.gen_py_import(module, functions);
             },
             Stmt::Return(expr) => {
                 if let Some(expr) = expr {
@@ -89,45 +1510,344 @@ impl CodeGen {
                     self.push_line("return;");
                 }
             },
+            Stmt::Global(_) => {},
+            Stmt::Raise(expr) => {
+                if let Some(expr) = expr {
+                    let expr_code = self.gen_expr(expr);
+                    self.push_line(&format!("throw {};", expr_code));
+                } else {
+                    self.push_line("throw;");
+                }
+            },
+            Stmt::Try(body, handlers, finally) => {
+                self.gen_try(body, handlers, finally.as_ref());
+            },
             Stmt::Pass => {
                 self.push_line("// pass");
+            },
+            Stmt::Line(line) => {
+                self.push_line(&format!("CVADRO_AT({});", line));
+            }
+        }
+    }
+    
+    fn gen_global(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Const(name, Some(ty), expr) => {
+                let expr_code = self.gen_expr(expr);
+                // std::string в C++17 не может быть constexpr
+                let qualifier = if *ty == Type::String { "const" } else { "constexpr" };
+                self.push_line(&format!("{} {} {} = {};", qualifier, self.type_to_cpp(ty), name, expr_code));
+            },
+            Stmt::Let(name, Some(ty), expr) if expr.is_literal() => {
+                let expr_code = self.gen_expr(expr);
+                self.push_line(&format!("{} {} = {};", self.type_to_cpp(ty), name, expr_code));
+            },
+            Stmt::Let(name, Some(ty), _) => {
+                self.push_line(&format!("{} {}{{}};", self.type_to_cpp(ty), name));
+            },
+            _ => {}
+        }
+    }
+    
+    fn gen_try(&mut self, body: &Block, handlers: &[ExceptClause], finally: Option<&Block>) {
+        self.push_line("{");
+        self.indent_level += 1;
+        
+        let finally_name = finally.map(|finally| {
+            self.temp_counter += 1;
+            let name = format!("cvadro_finally_{}", self.temp_counter);
+            self.push_line(&format!("cvadro_finally {}([&]() {{", name));
+            self.indent_level += 1;
+            self.gen_block(finally);
+            self.indent_level -= 1;
+            self.push_line("});");
+            self.push_line("try {");
+            self.indent_level += 1;
+            name
+        });
+        
+        if handlers.is_empty() {
+            // try без except: исключение уходит дальше, но finally выполняется
+            self.gen_block(body);
+        } else {
+            self.push_line("try {");
+            self.indent_level += 1;
+            self.gen_block(body);
+            self.indent_level -= 1;
+            for handler in handlers {
+                let catch = match (&handler.error_type, &handler.name) {
+                    (Some(error_type), Some(name)) => format!("{}& {}", error_type, name),
+                    (Some(error_type), None) => format!("{}&", error_type),
+                    _ => "...".to_string(),
+                };
+                self.push_line(&format!("}} catch ({}) {{", catch));
+                self.indent_level += 1;
+                self.gen_block(&handler.body);
+                self.indent_level -= 1;
             }
+            self.push_line("}");
+        }
+        
+        // Исключение из try и except выполнит finally и будет выброшено снова
+        if let Some(name) = finally_name {
+            self.indent_level -= 1;
+            self.push_line("} catch (...) {");
+            self.push_line(&format!("    {}.error = std::current_exception();", name));
+            self.push_line("}");
         }
+        
+        self.indent_level -= 1;
+        self.push_line("}");
     }
     
     fn gen_expr(&mut self, expr: &Expr) -> String {
         match expr {
             Expr::Literal(lit) => self.gen_literal(lit),
-            Expr::Variable(name) => name.clone(),
+            Expr::Variable(name) => self.cpp_name(name).to_string(),
             Expr::BinaryOp(left, op, right) => {
-                let left_code = self.gen_expr(left);
-                let right_code = self.gen_expr(right);
-                let op_str = self.gen_binop(op);
-                format!("({} {} {})", left_code, op_str, right_code)
+                let (left_code, right_code) = if matches!(op, BinOp::Shl | BinOp::Shr | BinOp::Pow) {
+                    (self.gen_expr(left), self.gen_expr(right))
+                } else {
+                    (self.gen_operand(left, right), self.gen_operand(right, left))
+                };
+                if let Some(helper) = self.arithmetic_helper(op) {
+                    return format!("{}({}, {})", helper, left_code, right_code);
+                }
+                format!("({})", self.gen_comparison(op, &left_code, &right_code))
+            },
+            Expr::Compare(first, rest) => {
+                // Промежуточные операнды сохраняются, чтобы вычислить их один раз и слева
+                // направо; сравнение прекращается на первом ложном звене
+                self.temp_counter += 1;
+                let mut left = format!("cvadro_operand_{}", self.temp_counter);
+                let mut code = format!("[&] {{ auto&& {} = {};", left, self.gen_operand(first, &rest[0].1));
+                for (i, (op, operand)) in rest.iter().enumerate() {
+                    let previous = if i == 0 { first.as_ref() } else { &rest[i - 1].1 };
+                    let right = self.gen_operand(operand, previous);
+                    if i + 1 == rest.len() {
+                        code.push_str(&format!(" return {}; }}()", self.gen_comparison(op, &left, &right)));
+                    } else {
+                        self.temp_counter += 1;
+                        let value = format!("cvadro_operand_{}", self.temp_counter);
+                        code.push_str(&format!(" auto&& {} = {};", value, right));
+                        code.push_str(&format!(" if (!({})) return false;", self.gen_comparison(op, &left, &value)));
+                        left = value;
+                    }
+                }
+                code
+            },
+            Expr::Cast(operand, ty) => {
+                let operand_code = self.gen_expr(operand);
+                // Целый литерал с суффиксом уже проверен на диапазон
+                if operand.int_literal().is_some() {
+                    return format!("static_cast<{}>({})", self.type_to_cpp(ty), operand_code);
+                }
+                self.require(CAST_RUNTIME);
+                format!("cvadro_cast<{}>({})", self.type_to_cpp(ty), operand_code)
+            },
+            Expr::Conditional(condition, then_expr, else_expr) => {
+                let cond_code = self.gen_expr(condition);
+                let narrowed = condition.none_check();
+                let mut then_code = self.gen_branch(then_expr, narrowed, true);
+                let mut else_code = self.gen_branch(else_expr, narrowed, false);
+                
+                // У None и значения нет общего типа в C++, поэтому значение явно
+                // оборачивается в std::optional
+                let is_none = |expr: &Expr| matches!(expr, Expr::Literal(Literal::None));
+                if is_none(else_expr) && !is_none(then_expr) {
+                    then_code = format!("std::optional({})", then_code);
+                } else if is_none(then_expr) && !is_none(else_expr) {
+                    else_code = format!("std::optional({})", else_code);
+                }
+                format!("({} ? {} : {})", cond_code, then_code, else_code)
             },
             Expr::UnaryOp(op, expr) => {
                 let expr_code = self.gen_expr(expr);
+                // -x переполняется только для наименьшего числа, а литерал им быть не может
+                if self.checked_arithmetic && matches!(op, UnaryOp::Neg) && !expr.is_literal() {
+                    self.require(CHECKED_RUNTIME);
+                    return format!("cvadro_neg({})", expr_code);
+                }
                 let op_str = self.gen_unaryop(op);
                 format!("({}{})", op_str, expr_code)
             },
-            Expr::Call(name, args) => {
-                let mut args_code = Vec::new();
-                for arg in args {
-                    args_code.push(self.gen_expr(arg));
+            Expr::Call(callee, args, kwargs) => {
+                let signature = match callee.as_ref() {
+                    Expr::Variable(name) => self.signatures.get(name),
+                    Expr::Field(object, name) => match object.as_ref() {
+                        Expr::Variable(module) if self.imported.contains(module) => {
+                            self.module_signatures.get(module).and_then(|exports| exports.get(name))
+                        },
+                        _ => None,
+                    },
+                    _ => None,
+                }.cloned();
+                
+                let args_code = match &signature {
+                    Some(signature) => self.gen_arguments(signature, args, kwargs),
+                    None => args.iter().map(|arg| self.gen_expr(arg)).collect(),
+                };
+                // s.upper(): метод строки, если s не модуль
+                if let Expr::Field(object, method) = callee.as_ref() {
+                    let is_module = matches!(object.as_ref(), Expr::Variable(module) if self.imported.contains(module));
+                    if !is_module && STRING_METHODS.contains(&method.as_str()) {
+                        self.require(STRING_RUNTIME);
+                        let mut method_args = vec![self.gen_expr(object)];
+                        method_args.extend(args_code);
+                        return format!("cvadro_method_{}({})", method, method_args.join(", "));
+                    }
+                }
+                // print(a, b, sep=", ", end=""): разделитель и окончание идут первыми аргументами
+                if matches!(callee.as_ref(), Expr::Variable(name) if name == "print") && signature.is_none() {
+                    let positional = args_code.len();
+                    let mut values = args_code;
+                    values.extend(kwargs.iter().map(|(_, value)| self.gen_expr(value)));
+                    // Порядок вычисления аргументов функции в C++ не задан, поэтому несколько
+                    // аргументов сначала вычисляются слева направо во временные переменные
+                    let mut temps = Vec::new();
+                    if values.len() > 1 {
+                        for (i, value) in values.iter_mut().enumerate() {
+                            let temp = format!("cvadro_arg_{}", i);
+                            temps.push(format!("const auto& {} = {};", temp, value));
+                            *value = temp;
+                        }
+                    }
+                    let call = if kwargs.is_empty() {
+                        format!("print({})", values.join(", "))
+                    } else {
+                        let option = |name: &str, default: &str| match kwargs.iter().position(|(kwarg, _)| kwarg == name) {
+                            Some(i) => values[positional + i].clone(),
+                            None => format!("std::string({})", cpp_string(default)),
+                        };
+                        let mut print_args = vec![option("sep", " "), option("end", "\n")];
+                        print_args.extend(values[..positional].iter().cloned());
+                        format!("cvadro_print({})", print_args.join(", "))
+                    };
+                    if temps.is_empty() {
+                        return call;
+                    }
+                    return format!("[&] {{ {} {}; }}()", temps.join(" "), call);
+                }
+                let builtin = match callee.as_ref() {
+                    Expr::Variable(name) if signature.is_none() => BUILTIN_FUNCTIONS.iter().find(|(builtin, _)| builtin == name),
+                    _ => None,
+                };
+                if let Some((name, cpp_name)) = builtin {
+                    if *name == "open" {
+                        self.require(FS_RUNTIME);
+                    }
+                    return format!("{}({})", cpp_name, args_code.join(", "));
+                }
+                let is_function = signature.is_some_and(|signature| signature.is_function);
+                let callee_code = match callee.as_ref() {
+                    // Имя в скобках отключает поиск по аргументам (ADL): иначе max("a", "b")
+                    // в C++ оказался бы неоднозначен с std::max
+                    Expr::Variable(name) if is_function => format!("({})", self.cpp_name(name)),
+                    Expr::Variable(name) => self.cpp_name(name).to_string(),
+                    Expr::Call(..) | Expr::Field(..) => self.gen_expr(callee),
+                    _ => format!("({})", self.gen_expr(callee)),
+                };
+                format!("{}({})", callee_code, args_code.join(", "))
+            },
+            Expr::Field(object, field) => {
+                if let Expr::Variable(name) = object.as_ref() {
+                    if self.imported.contains(name) {
+                        return format!("{}::{}", name, field);
+                    }
+                }
+                let object_code = self.gen_expr(object);
+                format!("{}.{}", object_code, field)
+            },
+            Expr::Index(object, index) => {
+                self.require(STRING_RUNTIME);
+                let object_code = self.gen_expr(object);
+                let index_code = self.gen_expr(index);
+                format!("cvadro_index({}, {})", object_code, index_code)
+            },
+            Expr::Slice(object, start, end) => {
+                self.require(STRING_RUNTIME);
+                let object_code = self.gen_expr(object);
+                let mut bound = |bound: &Option<Box<Expr>>| match bound {
+                    Some(bound) => self.gen_expr(bound),
+                    None => "std::nullopt".to_string(),
+                };
+                let (start_code, end_code) = (bound(start), bound(end));
+                format!("cvadro_slice({}, {}, {})", object_code, start_code, end_code)
+            },
+            Expr::List(items) => {
+                let items_code = items.iter()
+                    .map(|item| self.gen_expr(item))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                // Тип элементов задаёт первый элемент, не являющийся целым литералом:
+                // литералы к нему приводятся, как при проверке типов
+                let decider = items.iter().find(|item| item.int_literal().is_none());
+                match decider {
+                    _ if items.is_empty() => "{}".to_string(),
+                    Some(decider) if items.iter().any(|item| item.int_literal().is_some()) => {
+                        let decider_code = self.gen_expr(decider);
+                        format!("std::vector<std::decay_t<decltype({})>>{{{}}}", decider_code, items_code)
+                    },
+                    _ => format!("std::vector{{{}}}", items_code),
                 }
-                format!("{}({})", name, args_code.join(", "))
+            },
+            Expr::FString(parts) => {
+                self.require(FORMAT_RUNTIME);
+                let mut code = String::from("(std::string()");
+                for part in parts {
+                    match part {
+                        FStringPart::Text(text) => {
+                            code.push_str(&format!(" + {}", cpp_string(text)));
+                        },
+                        FStringPart::Expr(expr, None) => {
+                            code.push_str(&format!(" + cvadro_format({})", self.gen_expr(expr)));
+                        },
+                        FStringPart::Expr(expr, Some(spec)) => {
+                            code.push_str(&format!(
+                                " + cvadro_format({}, {}, {}, '{}', {}, {}, {})",
+                                self.gen_expr(expr),
+                                cpp_string(&spec.fill.to_string()),
+                                spec.align.map_or("0".to_string(), |c| format!("'{}'", c)),
+                                spec.sign.unwrap_or('-'),
+                                spec.width.unwrap_or(0),
+                                spec.precision.map_or(-1, |p| p as i64),
+                                spec.kind.map_or("0".to_string(), |c| format!("'{}'", c)),
+                            ));
+                        },
+                    }
+                }
+                code.push(')');
+                code
+            },
+            Expr::Lambda(params, body) => {
+                // Лямбды захватывают окружение по значению, чтобы их можно было
+                // безопасно сохранять и возвращать из функций
+                let params_code = params.iter()
+                    .map(|name| format!("auto {}", name))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let body_code = self.gen_expr(body);
+                format!("[=]({}) {{ return {}; }}", params_code, body_code)
             },
             Expr::If(condition, then_block, else_block) => {
                 let cond_code = self.gen_expr(condition);
+                let narrowed = condition.none_check();
                 self.push_line(&format!("if ({}) {{", cond_code));
                 self.indent_level += 1;
-                self.gen_block(then_block);
+                match narrowed {
+                    Some((name, true)) => self.gen_narrowed_block(name, then_block),
+                    _ => self.gen_block(then_block),
+                }
                 self.indent_level -= 1;
                 
                 if let Some(else_block) = else_block {
                     self.push_line("} else {");
                     self.indent_level += 1;
-                    self.gen_block(else_block);
+                    match narrowed {
+                        Some((name, false)) => self.gen_narrowed_block(name, else_block),
+                        _ => self.gen_block(else_block),
+                    }
                     self.indent_level -= 1;
                 }
                 self.push_line("}");
@@ -144,7 +1864,16 @@ impl CodeGen {
             },
             Expr::For(var, iterable, body) => {
                 // Простая реализация for - предполагаем, что iterable - это range
-                let iter_code = self.gen_expr(iterable);
+                self.require(STRING_RUNTIME);
+                let iter_code = match iterable.as_ref() {
+                    // open(path) в цикле читает файл по строке, а не списком целиком
+                    Expr::Call(callee, args, kwargs) if args.len() == 1 && kwargs.is_empty()
+                        && matches!(callee.as_ref(), Expr::Variable(name) if name == "open" && !self.signatures.contains_key(name)) => {
+                        self.require(FS_RUNTIME);
+                        format!("cvadro_file_lines({})", self.gen_expr(&args[0]))
+                    },
+                    _ => format!("cvadro_iter({})", self.gen_expr(iterable)),
+                };
                 self.push_line(&format!("for (auto {} : {}) {{", var, iter_code));
                 self.indent_level += 1;
                 self.gen_block(body);
@@ -155,12 +1884,87 @@ impl CodeGen {
         }
     }
     
+    // Расставляет аргументы в порядке параметров: именованные — на свои места,
+    // пропущенные — значениями по умолчанию, лишние позиционные — в список для *args
+    fn gen_arguments(&mut self, signature: &Signature, args: &[Expr], kwargs: &[(String, Expr)]) -> Vec<String> {
+        let fixed: Vec<&Param> = signature.params.iter().filter(|param| !param.variadic).collect();
+        let mut slots: Vec<Option<String>> = vec![None; fixed.len()];
+        
+        for (slot, arg) in slots.iter_mut().zip(args) {
+            *slot = Some(self.gen_expr(arg));
+        }
+        for (name, arg) in kwargs {
+            if let Some(i) = fixed.iter().position(|param| &param.name == name) {
+                slots[i] = Some(self.gen_expr(arg));
+            }
+        }
+        
+        if let Some(variadic) = signature.params.iter().find(|param| param.variadic) {
+            let extra: Vec<String> = args.iter().skip(fixed.len()).map(|arg| self.gen_expr(arg)).collect();
+            if extra.is_empty() && signature.is_local {
+                slots.push(Some("{}".to_string()));
+            } else if !extra.is_empty() {
+                // У обобщённой функции тип элементов выводит сам C++
+                let list_type = if signature.generic {
+                    "std::vector".to_string()
+                } else {
+                    self.type_to_cpp(&variadic.ty)
+                };
+                slots.push(Some(format!("{}{{{}}}", list_type, extra.join(", "))));
+            }
+        }
+        
+        // Пропущенные аргументы в конце покрывают значения по умолчанию в объявлении C++
+        while !signature.is_local && slots.last() == Some(&None) {
+            slots.pop();
+        }
+        slots.into_iter()
+            .enumerate()
+            .map(|(i, slot)| match slot {
+                Some(code) => code,
+                None => fixed[i].default.as_ref().map(|default| self.gen_expr(default)).unwrap_or_default(),
+            })
+            .collect()
+    }
+    
+    // Операнд бинарной операции. Целый литерал рядом с другим значением остаётся литералом
+    // C++ без суффикса и принимает его тип, как при проверке типов: в x + 1 при x: i32
+    // сложение выполняется в i32, а не в int64_t.
+    fn gen_operand(&mut self, expr: &Expr, other: &Expr) -> String {
+        match expr.int_literal() {
+            Some(value) if other.int_literal().is_none() && value < 0 => format!("({})", value),
+            Some(value) if other.int_literal().is_none() => value.to_string(),
+            _ => self.gen_expr(expr),
+        }
+    }
+    
     fn gen_literal(&self, lit: &Literal) -> String {
         match lit {
-            Literal::Int(n) => n.to_string(),
-            Literal::Float(f) => f.to_string(),
-            Literal::String(s) => format!("\"{}\"", s),
+            // int — это int64_t, а литерал без суффикса в C++ имеет тип int
+            Literal::Int(n) => format!("INT64_C({})", n),
+            // {:?} даёт кратчайшую запись, которая читается обратно в то же число,
+            // и всегда с точкой или порядком: 3.0, а не 3, иначе C++ счёл бы литерал целым
+            Literal::Float(f) if f.is_finite() => format!("{:?}", f),
+            Literal::Float(f) if f.is_nan() => "std::numeric_limits<double>::quiet_NaN()".to_string(),
+            Literal::Float(f) if *f > 0.0 => "std::numeric_limits<double>::infinity()".to_string(),
+            Literal::Float(_) => "(-std::numeric_limits<double>::infinity())".to_string(),
+            // Нулевой символ завершил бы строку C, поэтому длина передаётся явно
+            Literal::String(s) if s.contains('\0') => format!("std::string({}, {})", cpp_string(s), s.len()),
+            Literal::String(s) => format!("std::string({})", cpp_string(s)),
             Literal::Bool(b) => b.to_string(),
+            Literal::None => "std::nullopt".to_string(),
+        }
+    }
+    
+    // Сравнение или проверка вхождения двух уже сгенерированных операндов
+    fn gen_comparison(&mut self, op: &BinOp, left: &str, right: &str) -> String {
+        match op {
+            BinOp::In | BinOp::NotIn => {
+                self.require(STRING_RUNTIME);
+                let negation = if matches!(op, BinOp::NotIn) { "!" } else { "" };
+                format!("{}cvadro_contains({}, {})", negation, right, left)
+            },
+            _ => format!("{} {} {}", left, self.gen_binop(op), right),
         }
     }
     
@@ -170,7 +1974,11 @@ impl CodeGen {
             BinOp::Sub => "-",
             BinOp::Mul => "*",
             BinOp::Div => "/",
-            BinOp::Mod => "%",
+            BinOp::BitAnd => "&",
+            BinOp::BitOr => "|",
+            BinOp::BitXor => "^",
+            BinOp::Shl => "<<",
+            BinOp::Shr => ">>",
             BinOp::Eq => "==",
             BinOp::Ne => "!=",
             BinOp::Lt => "<",
@@ -179,32 +1987,123 @@ impl CodeGen {
             BinOp::Ge => ">=",
             BinOp::And => "&&",
             BinOp::Or => "||",
+            BinOp::Mod | BinOp::Pow | BinOp::FloorDiv | BinOp::In | BinOp::NotIn => {
+                unreachable!("{} lowers to a runtime helper", op)
+            },
+        }
+    }
+    
+    // %, ** и // ведут себя как в Python и вычисляются функциями среды выполнения
+    fn arithmetic_helper(&mut self, op: &BinOp) -> Option<&'static str> {
+        let checked = match op {
+            BinOp::Add => "cvadro_add",
+            BinOp::Sub => "cvadro_sub",
+            BinOp::Mul => "cvadro_mul",
+            BinOp::Div => "cvadro_div",
+            BinOp::Pow => "cvadro_checked_pow",
+            BinOp::Shl => "cvadro_shl",
+            BinOp::Shr => "cvadro_shr",
+            _ => "",
+        };
+        if self.checked_arithmetic && !checked.is_empty() {
+            self.require(CHECKED_RUNTIME);
+            return Some(checked);
         }
+        let helper = match op {
+            BinOp::Mod => "cvadro_mod",
+            BinOp::Pow => "cvadro_pow",
+            BinOp::FloorDiv => "cvadro_floordiv",
+            _ => return None,
+        };
+        self.require(ARITHMETIC_RUNTIME);
+        Some(helper)
     }
     
     fn gen_unaryop(&self, op: &UnaryOp) -> &'static str {
         match op {
             UnaryOp::Neg => "-",
             UnaryOp::Not => "!",
+            UnaryOp::BitNot => "~",
         }
     }
     
     fn gen_block(&mut self, block: &Block) {
-        for stmt in &block.0 {
+        self.gen_stmts(&block.0);
+    }
+    
+    fn gen_stmts<'a>(&mut self, stmts: impl IntoIterator<Item = &'a Stmt>) {
+        let mut scopes = 0;
+        let mut shadowed = Vec::new();
+        for stmt in stmts {
+            // Вложенная функция видна до конца блока и может вызывать саму себя
+            if let Stmt::Function(f) = stmt {
+                let signature = Signature { is_local: true, ..function_signature(f) };
+                shadowed.push((f.name.clone(), self.signatures.insert(f.name.clone(), signature)));
+            }
             self.gen_stmt(stmt);
+            
+            // После if x == None: return переменная x до конца блока не None
+            if let Stmt::Expr(Expr::If(condition, then_block, _)) = stmt {
+                if let Some((name, false)) = condition.none_check() {
+                    if then_block.always_exits() {
+                        self.open_narrowed_scope(name);
+                        scopes += 1;
+                    }
+                }
+            }
+        }
+        for _ in 0..scopes {
+            self.indent_level -= 1;
+            self.push_line("}");
+        }
+        for (name, signature) in shadowed.into_iter().rev() {
+            match signature {
+                Some(signature) => self.signatures.insert(name, signature),
+                None => self.signatures.remove(&name),
+            };
         }
     }
     
+    // В C++ значение std::optional достаётся через *x. Внутри блока имя x
+    // перекрывается ссылкой на само значение, чтобы код блока не менялся.
+    fn gen_narrowed_block(&mut self, name: &str, block: &Block) {
+        self.open_narrowed_scope(name);
+        self.gen_block(block);
+        self.indent_level -= 1;
+        self.push_line("}");
+    }
+    
+    // Ветвь условного выражения. Если в ней переменная точно не None, ветвь
+    // вычисляется в лямбде, где имя перекрыто ссылкой на значение.
+    fn gen_branch(&mut self, expr: &Expr, narrowed: Option<(&str, bool)>, branch: bool) -> String {
+        match narrowed {
+            Some((name, when_true)) if when_true == branch => {
+                self.temp_counter += 1;
+                let value = format!("cvadro_value_{}", self.temp_counter);
+                let code = self.gen_expr(expr);
+                format!("[&] {{ auto& {} = *{}; auto& {} = {}; return {}; }}()", value, name, name, value, code)
+            },
+            _ => self.gen_expr(expr),
+        }
+    }
+    
+    fn open_narrowed_scope(&mut self, name: &str) {
+        self.temp_counter += 1;
+        let value = format!("cvadro_value_{}", self.temp_counter);
+        self.push_line(&format!("auto& {} = *{};", value, name));
+        self.push_line("{");
+        self.indent_level += 1;
+        self.push_line(&format!("auto& {} = {};", name, value));
+    }
+    
     fn gen_function(&mut self, f: &FunctionDef) {
         // Конвертация типов cvadroscript → C++
         let return_type = self.type_to_cpp(&f.return_type);
         
-        let params = f.params.iter()
-            .map(|(name, ty)| format!("{} {}", self.type_to_cpp(ty), name))
-            .collect::<Vec<_>>()
-            .join(", ");
+        let params = self.gen_params(&f.params, false);
         
-        self.push_line(&format!("{} {}({}) {{", return_type, f.name, params));
+        self.gen_template_header(&f.type_params);
+        self.push_line(&format!("{} {}({}) {{", return_type, self.cpp_name(&f.name), params));
         self.indent_level += 1;
         
         // Генерация тела функции
@@ -215,14 +2114,182 @@ impl CodeGen {
         self.push_line(""); // Пустая строка после функции
     }
     
-    fn type_to_cpp(&self, ty: &Type) -> &'static str {
+    // Вложенная функция становится лямбдой, которая, как и лямбды CvadroScript,
+    // захватывает окружение по значению. Чтобы функция могла вызывать саму себя,
+    // замыкание получает себя первым аргументом, а внутри тела её имя — обёртка над ним.
+    fn gen_nested_function(&mut self, f: &FunctionDef) {
+        let return_type = self.type_to_cpp(&f.return_type);
+        let function_type = self.type_to_cpp(&f.function_type());
+        let params = self.gen_params(&f.params, false);
+        let args: String = f.params.iter().map(|param| format!(", {}", param.name)).collect();
+        let name = self.cpp_name(&f.name);
+        
+        self.temp_counter += 1;
+        let closure = format!("cvadro_closure_{}", self.temp_counter);
+        let self_params = if params.is_empty() {
+            "const auto& cvadro_self".to_string()
+        } else {
+            format!("const auto& cvadro_self, {}", params)
+        };
+        self.push_line(&format!("auto {} = [=]({}) -> {} {{", closure, self_params, return_type));
+        self.indent_level += 1;
+        self.push_line(&format!(
+            "auto {} = [&]({}) -> {} {{ return cvadro_self(cvadro_self{}); }};",
+            name, params, return_type, args
+        ));
+        self.gen_block(&f.body);
+        self.indent_level -= 1;
+        self.push_line("};");
+        self.push_line(&format!(
+            "{} {} = [{}]({}) -> {} {{ return {}({}{}); }};",
+            function_type, name, closure, params, return_type, closure, closure, args
+        ));
+    }
+    
+    // Значения по умолчанию переносятся в объявление C++; *args по умолчанию пуст
+    fn gen_params(&mut self, params: &[Param], with_defaults: bool) -> String {
+        params.iter()
+            .map(|param| {
+                let declaration = format!("{} {}", self.type_to_cpp(&param.ty), param.name);
+                match &param.default {
+                    _ if !with_defaults => declaration,
+                    Some(default) => format!("{} = {}", declaration, self.gen_expr(default)),
+                    None if param.variadic => format!("{} = {{}}", declaration),
+                    None => declaration,
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+    
+    // Обёртки над функциями модуля Python, вызывающие их через мост
+    fn gen_py_import(&mut self, module: &str, functions: &[FunctionDef]) {
+        self.require(PYTHON_RUNTIME);
+        self.push_line(&format!("namespace {} {{", module));
+        for f in functions {
+            let return_type = self.type_to_cpp(&f.return_type);
+            let params = self.gen_params(&f.params, true);
+            let args = f.params.iter()
+                .map(|param| format!("cvadro_py::arg({})", param.name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            
+            self.push_line(&format!("{} {}({}) {{", return_type, f.name, params));
+            self.indent_level += 1;
+            self.push_line(&format!(
+                "return cvadro_py::call<{}>({}, {}, {}, {{{}}});",
+                return_type,
+                cpp_string(&self.module_dir),
+                cpp_string(module),
+                cpp_string(&f.name),
+                args
+            ));
+            self.indent_level -= 1;
+            self.push_line("}");
+        }
+        self.push_line(&format!("}} // namespace {}", module));
+        self.push_line("");
+    }
+    
+    fn gen_struct(&mut self, s: &StructDef) {
+        self.gen_template_header(&s.type_params);
+        self.push_line(&format!("struct {} {{", s.name));
+        self.indent_level += 1;
+        
+        for (name, ty) in &s.fields {
+            self.push_line(&format!("{} {};", self.type_to_cpp(ty), name));
+        }
+        
+        // Конструктор по всем полям: Point(1, 2), а для обобщённых структур
+        // он же позволяет C++ вывести параметры шаблона из аргументов
+        if !s.fields.is_empty() {
+            let params = s.fields.iter()
+                .map(|(name, ty)| format!("{} {}", self.type_to_cpp(ty), name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let inits = s.fields.iter()
+                .map(|(name, _)| format!("{}({})", name, name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            self.push_line(&format!("{}({}) : {} {{}}", s.name, params, inits));
+            // Конструктор по умолчанию нужен глобальным переменным, которые получают значение в main
+            self.push_line(&format!("{}() = default;", s.name));
+        }
+        
+        // Вывод для print: Point(x=1, y=2). Шаблон компилируется, только если структуру
+        // действительно печатают, так что поля-функции и прочие непечатаемые поля не мешают.
+        self.push_line("template<typename Out>");
+        self.push_line("void cvadro_write_fields(Out& out) const {");
+        self.indent_level += 1;
+        self.push_line(&format!("out << \"{}(\";", s.name));
+        for (i, (name, _)) in s.fields.iter().enumerate() {
+            let separator = if i == 0 { "" } else { ", " };
+            self.push_line(&format!("out << \"{}{}=\";", separator, name));
+            self.push_line(&format!("cvadro_write(out, {}, true);", name));
+        }
+        self.push_line("out << \")\";");
+        self.indent_level -= 1;
+        self.push_line("}");
+        
+        self.indent_level -= 1;
+        self.push_line("};");
+        self.push_line("");
+    }
+    
+    fn gen_template_header(&mut self, type_params: &[String]) {
+        if type_params.is_empty() {
+            return;
+        }
+        let params = type_params.iter()
+            .map(|name| format!("typename {}", name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.push_line(&format!("template<{}>", params));
+    }
+    
+    fn type_to_cpp(&self, ty: &Type) -> String {
         match ty {
-            Type::Int => "int",
-            Type::Float => "double",
-            Type::String => "std::string",
-            Type::Bool => "bool", 
-            Type::Void => "void",
-            Type::Custom(_) => "auto", // Для пользовательских типов используем auto
+            Type::Int => "int64_t".to_string(),
+            Type::Float => "double".to_string(),
+            Type::I8 => "int8_t".to_string(),
+            Type::I16 => "int16_t".to_string(),
+            Type::I32 => "int32_t".to_string(),
+            Type::U8 => "uint8_t".to_string(),
+            Type::U16 => "uint16_t".to_string(),
+            Type::U32 => "uint32_t".to_string(),
+            Type::U64 => "uint64_t".to_string(),
+            Type::F32 => "float".to_string(),
+            Type::Char => "char32_t".to_string(),
+            Type::String => "std::string".to_string(),
+            Type::Bool => "bool".to_string(),
+            Type::Void => "void".to_string(),
+            Type::List(item) => format!("std::vector<{}>", self.type_to_cpp(item)),
+            Type::Function(params, return_type) => {
+                let params = params.iter()
+                    .map(|ty| self.type_to_cpp(ty))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("std::function<{}({})>", self.type_to_cpp(return_type), params)
+            },
+            Type::Optional(inner) => format!("std::optional<{}>", self.type_to_cpp(inner)),
+            Type::None => "std::nullopt_t".to_string(),
+            Type::Custom(name) => name.replace('.', "::"),
+            Type::Generic(name, args) => {
+                let args = args.iter()
+                    .map(|ty| self.type_to_cpp(ty))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("{}<{}>", name.replace('.', "::"), args)
+            },
+        }
+    }
+    
+    // Функция main главного модуля вызывается из сгенерированного main C++
+    fn cpp_name<'a>(&self, name: &'a str) -> &'a str {
+        if self.in_entry && name == "main" {
+            "cvadro_main"
+        } else {
+            name
         }
     }
     
@@ -233,4 +2300,101 @@ impl CodeGen {
         self.output.push_str(line);
         self.output.push('\n');
     }
+}
+
+// Упорядочивает структуры так, чтобы поле-структура была определена раньше,
+// чем структура, которая её содержит. Списки и функции полного типа не требуют.
+fn order_structs<'a>(structs: &[&'a StructDef]) -> Vec<&'a StructDef> {
+    fn visit<'a>(def: &'a StructDef, structs: &[&'a StructDef], ordered: &mut Vec<&'a StructDef>, visiting: &mut Vec<String>) {
+        if ordered.iter().any(|done| done.name == def.name) || visiting.contains(&def.name) {
+            return;
+        }
+        visiting.push(def.name.clone());
+        let mut names = Vec::new();
+        for (_, ty) in &def.fields {
+            value_types(ty, &mut names);
+        }
+        for name in names {
+            if let Some(dependency) = structs.iter().find(|other| other.name == name) {
+                visit(dependency, structs, ordered, visiting);
+            }
+        }
+        visiting.pop();
+        ordered.push(def);
+    }
+    
+    let mut ordered = Vec::new();
+    for def in structs {
+        visit(def, structs, &mut ordered, &mut Vec::new());
+    }
+    ordered
+}
+
+// Имена типов, которые входят в тип по значению
+fn value_types(ty: &Type, names: &mut Vec<String>) {
+    match ty {
+        Type::Custom(name) => names.push(name.clone()),
+        Type::Generic(name, args) => {
+            names.push(name.clone());
+            for arg in args {
+                value_types(arg, names);
+            }
+        },
+        Type::Optional(inner) => value_types(inner, names),
+        _ => {}
+    }
+}
+
+fn function_signature(f: &FunctionDef) -> Signature {
+    Signature {
+        is_function: true,
+        generic: !f.type_params.is_empty(),
+        is_local: false,
+        params: f.params.clone(),
+    }
+}
+
+// Функции и структуры, объявленные в самом модуле
+fn local_signatures(stmts: &[Stmt]) -> HashMap<String, Signature> {
+    stmts.iter()
+        .filter_map(|stmt| match stmt {
+            Stmt::Function(f) => Some((f.name.clone(), function_signature(f))),
+            Stmt::Struct(s) => Some((s.name.clone(), Signature {
+                is_function: false,
+                generic: !s.type_params.is_empty(),
+                is_local: false,
+                params: s.constructor_params(),
+            })),
+            _ => None,
+        })
+        .collect()
+}
+
+// Среда выполнения стандартного модуля
+fn std_runtime(module: &str) -> Option<&'static str> {
+    match module {
+        "fs" => Some(FS_RUNTIME),
+        _ => None,
+    }
+}
+
+// Строковый литерал C++ с экранированными спецсимволами. Остальные управляющие
+// символы записываются восьмеричными кодами из трёх цифр: в отличие от \x, такая
+// последовательность не поглощает следующие за ней цифры. Прочий текст, в том
+// числе кириллица, остаётся как есть в UTF-8.
+fn cpp_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_ascii_control() => out.push_str(&format!("\\{:03o}", c as u32)),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+    out
 }
\ No newline at end of file
@@ -1,20 +1,38 @@
+use crate::ast::{Type, FormatSpec};
+
+// Суффиксы, задающие тип числового литерала
+const NUMBER_SUFFIXES: [&str; 10] = ["i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64", "f32", "f64"];
+
+#[derive(Debug, PartialEq)]
+pub enum FStringChunk {
+    Text(String),
+    // Исходный текст выражения и необязательная спецификация формата
+    Code(String, Option<FormatSpec>),
+}
+
 #[derive(Debug, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Token {
     // Ключевые слова
-    Def, If, Else, While, For, In, Return, Let, Extern, Struct, Pass, And, Or, Not, True, False, 
+    Def, If, Else, While, For, In, Return, Let, Extern, Struct, Pass, And, Or, Not, True, False, Lambda, Import, From, PyImport, Try, Except, Finally, Raise, As, None, Const, Global,
     
     // Идентификаторы и литералы
     Ident(String),
-    Int(i64),
-    Float(f64),
+    // Число и тип из его суффикса: 200u8
+    Int(i64, Option<Type>),
+    Float(f64, Option<Type>),
     String(String),
+    FString(Vec<FStringChunk>),
     
     // Операторы
-    Plus, Minus, Star, Slash, Percent,
+    Plus, Minus, Star, Slash, Percent, StarStar, SlashSlash,
+    Amp, Pipe, Caret, Tilde, Shl, Shr,
     Eq, EqEq, Ne, Lt, Gt, Le, Ge,
+    PlusEq, MinusEq, StarEq, SlashEq, PercentEq, StarStarEq, SlashSlashEq,
+    AmpEq, PipeEq, CaretEq, ShlEq, ShrEq,
     
     // Разделители
-    LParen, RParen, Colon, Comma, Newline,
+    LParen, RParen, LBracket, RBracket, Colon, Comma, Dot, Question, Newline,
     Indent, Dedent, EOF,
     
     // Специальные
@@ -25,6 +43,10 @@ pub struct Lexer {
     source: Vec<char>,
     pos: usize,
     indent_stack: Vec<usize>,
+    pending_dedents: usize,
+    line: usize,
+    // Ошибки в записи литералов; разбор продолжается, чтобы найти их все
+    errors: Vec<String>,
 }
 
 impl Lexer {
@@ -33,10 +55,40 @@ impl Lexer {
             source: input.chars().collect(),
             pos: 0,
             indent_stack: vec![0],
+            pending_dedents: 0,
+            line: 1,
+            errors: Vec::new(),
+        }
+    }
+    
+    // Все токены до EOF включительно и номер строки каждого из них
+    pub fn tokenize(&mut self) -> Result<(Vec<Token>, Vec<usize>), Vec<String>> {
+        let mut tokens = Vec::new();
+        let mut lines = Vec::new();
+        
+        while let Some(token) = self.next_token() {
+            let is_eof = token == Token::EOF;
+            tokens.push(token);
+            lines.push(self.line);
+            if is_eof {
+                break;
+            }
+        }
+        
+        if self.errors.is_empty() {
+            Ok((tokens, lines))
+        } else {
+            Err(std::mem::take(&mut self.errors))
         }
     }
     
     pub fn next_token(&mut self) -> Option<Token> {
+        // Выход сразу из нескольких блоков даёт несколько Dedent подряд
+        if self.pending_dedents > 0 {
+            self.pending_dedents -= 1;
+            return Some(Token::Dedent);
+        }
+        
         self.skip_whitespace();
         
         match self.current() {
@@ -46,22 +98,32 @@ impl Lexer {
                 self.skip_comment();
                 self.next_token()
             },
+            Some('f') if matches!(self.peek_next(), Some('"') | Some('\'')) => self.read_fstring(),
             Some(c) if c.is_alphabetic() || *c == '_' => self.read_identifier(),
-            Some(c) if c.is_digit(10) => self.read_number(),
+            Some(c) if c.is_ascii_digit() => self.read_number(),
+            Some('.') if self.peek_next().is_some_and(|c| c.is_ascii_digit()) => self.read_number(),
             Some('"') | Some('\'') => self.read_string(),
-            Some('+') => { self.advance(); Some(Token::Plus) },
+            Some('+') => self.read_operator(Token::Plus, Token::PlusEq),
             Some('-') => self.read_minus_or_arrow(),
-            Some('*') => { self.advance(); Some(Token::Star) },
-            Some('/') => { self.advance(); Some(Token::Slash) },
-            Some('%') => { self.advance(); Some(Token::Percent) },
+            Some('*') => self.read_doubled_operator('*', [Token::Star, Token::StarEq, Token::StarStar, Token::StarStarEq]),
+            Some('/') => self.read_doubled_operator('/', [Token::Slash, Token::SlashEq, Token::SlashSlash, Token::SlashSlashEq]),
+            Some('%') => self.read_operator(Token::Percent, Token::PercentEq),
+            Some('&') => self.read_operator(Token::Amp, Token::AmpEq),
+            Some('|') => self.read_operator(Token::Pipe, Token::PipeEq),
+            Some('^') => self.read_operator(Token::Caret, Token::CaretEq),
+            Some('~') => { self.advance(); Some(Token::Tilde) },
             Some('=') => self.read_equals(),
             Some('!') => self.read_not_equals(),
-            Some('<') => self.read_less(),
-            Some('>') => self.read_greater(),
+            Some('<') => self.read_doubled_operator('<', [Token::Lt, Token::Le, Token::Shl, Token::ShlEq]),
+            Some('>') => self.read_doubled_operator('>', [Token::Gt, Token::Ge, Token::Shr, Token::ShrEq]),
             Some('(') => { self.advance(); Some(Token::LParen) },
             Some(')') => { self.advance(); Some(Token::RParen) },
+            Some('[') => { self.advance(); Some(Token::LBracket) },
+            Some(']') => { self.advance(); Some(Token::RBracket) },
+            Some('.') => { self.advance(); Some(Token::Dot) },
             Some(':') => { self.advance(); Some(Token::Colon) },
             Some(',') => { self.advance(); Some(Token::Comma) },
+            Some('?') => { self.advance(); Some(Token::Question) },
             _ => {
                 self.advance();
                 self.next_token()
@@ -70,13 +132,31 @@ impl Lexer {
     }
     
     fn handle_indent(&mut self) -> Option<Token> {
-        self.advance(); // Пропускаем '\n'
-        let mut indent_level = 0;
+        let mut indent_level;
         
-        // Считаем пробелы/табы
-        while let Some(' ') | Some('\t') = self.current() {
-            indent_level += 1;
-            self.advance();
+        loop {
+            self.advance(); // Пропускаем '\n'
+            self.line += 1;
+            indent_level = 0;
+            
+            // Считаем пробелы/табы
+            while let Some(' ') | Some('\t') = self.current() {
+                indent_level += 1;
+                self.advance();
+            }
+            
+            // Пустые строки и строки из одного комментария не влияют на отступы
+            match self.current() {
+                Some('\r') => self.advance(),
+                Some('#') => self.skip_comment(),
+                _ => {}
+            }
+            match self.current() {
+                Some('\n') => continue,
+                None => indent_level = 0,
+                _ => {}
+            }
+            break;
         }
         
         let current_indent = *self.indent_stack.last().unwrap();
@@ -85,7 +165,11 @@ impl Lexer {
             self.indent_stack.push(indent_level);
             Some(Token::Indent)
         } else if indent_level < current_indent {
-            self.indent_stack.pop();
+            while *self.indent_stack.last().unwrap() > indent_level {
+                self.indent_stack.pop();
+                self.pending_dedents += 1;
+            }
+            self.pending_dedents -= 1;
             Some(Token::Dedent)
         } else {
             Some(Token::Newline)
@@ -96,6 +180,10 @@ impl Lexer {
         self.source.get(self.pos)
     }
     
+    fn peek_next(&self) -> Option<&char> {
+        self.source.get(self.pos + 1)
+    }
+    
     fn advance(&mut self) {
         self.pos += 1;
     }
@@ -147,31 +235,207 @@ impl Lexer {
             "not" => Token::Not,
             "True" => Token::True,
             "False" => Token::False,
+            "None" => Token::None,
+            "const" => Token::Const,
+            "global" => Token::Global,
+            "lambda" => Token::Lambda,
+            "import" => Token::Import,
+            "from" => Token::From,
+            "pyimport" => Token::PyImport,
+            "try" => Token::Try,
+            "except" => Token::Except,
+            "finally" => Token::Finally,
+            "raise" => Token::Raise,
+            "as" => Token::As,
             _ => Token::Ident(ident),
         })
     }
     
+    // Числа: 42, 1_000_000, 0xFF, 0o17, 0b1010, 3.14, 1., .5, 1e-9, 2.5E+3.
+    // Суффикс задаёт тип литерала: 200u8, 0xFFu16, 1.5f32; f делает литерал дробным: 1f == 1.0.
     fn read_number(&mut self) -> Option<Token> {
+        if self.current() == Some(&'0') {
+            let radix = match self.peek_next() {
+                Some('x') | Some('X') => Some((16, "шестнадцатеричном")),
+                Some('o') | Some('O') => Some((8, "восьмеричном")),
+                Some('b') | Some('B') => Some((2, "двоичном")),
+                _ => None,
+            };
+            if let Some((radix, kind)) = radix {
+                self.advance();
+                self.advance();
+                return Some(self.read_radix_number(radix, kind));
+            }
+        }
+        
         let start = self.pos;
         let mut is_float = false;
+        self.read_digits();
         
-        while let Some(c) = self.current() {
-            if c.is_digit(10) {
+        // Точка относится к числу, только если за ней не начинается имя: 1.5, 1. и 1.e5, но не 1.x
+        let name_follows = matches!(self.peek_next(), Some(c) if c.is_alphabetic() || *c == '_');
+        if self.current() == Some(&'.') && (!name_follows || self.exponent_at(self.pos + 1)) {
+            is_float = true;
+            self.advance();
+            self.read_digits();
+        }
+        
+        if self.exponent_at(self.pos) {
+            is_float = true;
+            self.advance();
+            if matches!(self.current(), Some('+') | Some('-')) {
                 self.advance();
-            } else if *c == '.' && !is_float {
-                is_float = true;
+            }
+            self.read_digits();
+        }
+        
+        let text: String = self.source[start..self.pos].iter().collect();
+        let suffix = self.read_suffix();
+        self.check_separators(&text, &text);
+        let digits = text.replace('_', "");
+        
+        let ty = match suffix.as_str() {
+            "" => None,
+            "f" => Some(Type::Float),
+            _ => match self.suffix_type(&text, &suffix) {
+                Some(ty) => Some(ty),
+                None => return Some(Token::Int(0, None)),
+            }
+        };
+        if ty.as_ref().is_some_and(Type::is_float) {
+            is_float = true;
+        } else if is_float && ty.is_some() {
+            self.error(format!("дробное число {} не может иметь суффикс {}", text, suffix));
+        }
+        
+        if is_float {
+            let value: f64 = digits.parse().unwrap_or(0.0);
+            let limit = if ty == Some(Type::F32) { f32::MAX as f64 } else { f64::MAX };
+            if value.abs() > limit {
+                self.error(format!("число {}{} не помещается в {}", text, suffix, ty.as_ref().unwrap_or(&Type::Float)));
+            }
+            return Some(Token::Float(value, ty.filter(|ty| *ty != Type::Float)));
+        }
+        
+        if digits.len() > 1 && digits.starts_with('0') && digits.chars().any(|c| c != '0') {
+            self.error(format!("число {} не может начинаться с нуля, восьмеричные числа записываются как 0o{}", text, digits.trim_start_matches('0')));
+        }
+        Some(self.int_token(digits.parse().ok(), ty, &format!("{}{}", text, suffix)))
+    }
+    
+    fn read_radix_number(&mut self, radix: u32, kind: &str) -> Token {
+        let start = self.pos;
+        while let Some(c) = self.current() {
+            if c.is_alphanumeric() || *c == '_' {
                 self.advance();
             } else {
                 break;
             }
         }
         
-        let num_str: String = self.source[start..self.pos].iter().collect();
-        Some(if is_float {
-            Token::Float(num_str.parse().unwrap_or(0.0))
-        } else {
-            Token::Int(num_str.parse().unwrap_or(0))
-        })
+        let literal: String = self.source[start - 2..self.pos].iter().collect();
+        let mut text: String = self.source[start..self.pos].iter().collect();
+        // Суффикс целого типа начинается с i или u, которых нет среди цифр: 0xFFu8
+        let mut ty = None;
+        if let Some(pos) = text.find(['i', 'u']) {
+            let suffix = text.split_off(pos);
+            match self.suffix_type(&literal[..literal.len() - suffix.len()], &suffix) {
+                Some(suffix_type) => ty = Some(suffix_type),
+                None => return Token::Int(0, None),
+            }
+        }
+        
+        let prefix = &literal[..2];
+        if let Some(c) = text.chars().find(|c| *c != '_' && !c.is_digit(radix)) {
+            self.error(format!("недопустимая цифра '{}' в {} числе {}", c, kind, literal));
+            return Token::Int(0, None);
+        }
+        if text.is_empty() {
+            self.error(format!("после {} нет цифр", prefix));
+            return Token::Int(0, None);
+        }
+        // После префикса _ допустим: 0x_FF
+        self.check_separators(text.strip_prefix('_').unwrap_or(&text), &literal);
+        
+        if ty.as_ref().is_some_and(Type::is_float) {
+            self.error(format!("число {} в {} записи не может иметь дробный тип", literal, kind));
+            return Token::Int(0, None);
+        }
+        let value = u64::from_str_radix(&text.replace('_', ""), radix).ok();
+        self.int_token(value, ty, &literal)
+    }
+    
+    // Тип, который задаёт суффикс числа, или None с сообщением об ошибке
+    fn suffix_type(&mut self, text: &str, suffix: &str) -> Option<Type> {
+        match Type::numeric(suffix) {
+            Some(ty) if NUMBER_SUFFIXES.contains(&suffix) => Some(ty),
+            _ => {
+                self.error(format!("неизвестный суффикс числа {}{}", text, suffix));
+                None
+            }
+        }
+    }
+    
+    // Целое число должно помещаться в свой тип: int, если суффикса нет
+    // Значения u64 больше i64::MAX хранятся в i64 с тем же битовым представлением
+    fn int_token(&mut self, value: Option<u64>, ty: Option<Type>, literal: &str) -> Token {
+        let ty = ty.filter(|ty| *ty != Type::Int);
+        let max = ty.as_ref().and_then(Type::int_range).map_or(i64::MAX as i128, |(_, max)| max);
+        match value {
+            Some(value) if value as i128 <= max => Token::Int(value as i64, ty),
+            _ => {
+                self.error(format!("число {} не помещается в {}", literal, ty.unwrap_or(Type::Int)));
+                Token::Int(0, None)
+            }
+        }
+    }
+    
+    fn read_digits(&mut self) {
+        while let Some(c) = self.current() {
+            if c.is_ascii_digit() || *c == '_' {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+    }
+    
+    fn read_suffix(&mut self) -> String {
+        let mut suffix = String::new();
+        while let Some(&c) = self.current() {
+            if c.is_alphanumeric() || c == '_' {
+                suffix.push(c);
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        suffix
+    }
+    
+    // Как в Python, _ допускается только между цифрами: 1_000, но не 1__000 или 1000_
+    // Порядок числа с позиции pos: e5, E-9, e+3
+    fn exponent_at(&self, pos: usize) -> bool {
+        if !matches!(self.source.get(pos), Some('e') | Some('E')) {
+            return false;
+        }
+        let sign = matches!(self.source.get(pos + 1), Some('+') | Some('-'));
+        let digit = self.source.get(pos + if sign { 2 } else { 1 });
+        digit.is_some_and(|c| c.is_ascii_digit())
+    }
+    
+    fn check_separators(&mut self, digits: &str, literal: &str) {
+        let chars: Vec<char> = digits.chars().collect();
+        let misplaced = chars.iter().enumerate().any(|(i, c)| {
+            *c == '_' && !(i > 0 && chars[i - 1].is_alphanumeric() && chars.get(i + 1).is_some_and(|c| c.is_alphanumeric()))
+        });
+        if misplaced {
+            self.error(format!("в числе {} знак _ должен стоять между цифрами", literal));
+        }
+    }
+    
+    fn error(&mut self, message: String) {
+        self.errors.push(format!("строка {}: {}", self.line, message));
     }
     
     fn read_string(&mut self) -> Option<Token> {
@@ -185,15 +449,8 @@ impl Lexer {
                 break;
             }
             if *c == '\\' {
-                self.advance();
-                match self.current() {
-                    Some('n') => string.push('\n'),
-                    Some('t') => string.push('\t'),
-                    Some('r') => string.push('\r'),
-                    Some('\\') => string.push('\\'),
-                    Some('"') => string.push('"'),
-                    Some('\'') => string.push('\''),
-                    Some(c) => string.push(*c),
+                match self.read_escape() {
+                    Some(c) => string.push(c),
                     None => break,
                 }
             } else {
@@ -204,13 +461,171 @@ impl Lexer {
         Some(Token::String(string))
     }
     
-    fn read_minus_or_arrow(&mut self) -> Option<Token> {
-        self.advance(); // Пропускаем '-'
-        if let Some('>') = self.current() {
+    // Текущий символ — '\\'; оставляет позицию на экранированном символе
+    fn read_escape(&mut self) -> Option<char> {
+        self.advance();
+        match self.current() {
+            Some('n') => Some('\n'),
+            Some('t') => Some('\t'),
+            Some('r') => Some('\r'),
+            Some('0') => Some('\0'),
+            // \xHH — символ с кодом HH, как в Python: "\xe9" — это «é»
+            Some('x') => {
+                let digits: String = self.source[self.pos + 1..].iter().take(2).collect();
+                match u8::from_str_radix(&digits, 16) {
+                    Ok(code) if digits.len() == 2 && digits.chars().all(|c| c.is_ascii_hexdigit()) => {
+                        self.advance();
+                        self.advance();
+                        Some(char::from(code))
+                    },
+                    _ => {
+                        self.error("после \\x нужны две шестнадцатеричные цифры".to_string());
+                        Some('x')
+                    }
+                }
+            },
+            Some(c) => Some(*c),
+            None => None,
+        }
+    }
+    
+    // f"x = {x}, y = {y:.2f}": текст и выражения в фигурных скобках.
+    // Выражения разбирает парсер; {{ и }} означают сами скобки.
+    fn read_fstring(&mut self) -> Option<Token> {
+        self.advance(); // Пропускаем 'f'
+        let quote = *self.current()?;
+        self.advance(); // Пропускаем открывающую кавычку
+        
+        let mut chunks = Vec::new();
+        let mut text = String::new();
+        while let Some(&c) = self.current() {
+            match c {
+                _ if c == quote => {
+                    self.advance(); // Пропускаем закрывающую кавычку
+                    break;
+                },
+                '\\' => match self.read_escape() {
+                    Some(c) => text.push(c),
+                    None => break,
+                },
+                '{' if self.peek_next() == Some(&'{') => {
+                    text.push('{');
+                    self.advance();
+                },
+                '}' if self.peek_next() == Some(&'}') => {
+                    text.push('}');
+                    self.advance();
+                },
+                '}' => self.error("одиночная } в f-строке: саму скобку записывают как }}".to_string()),
+                '{' => {
+                    if !text.is_empty() {
+                        chunks.push(FStringChunk::Text(std::mem::take(&mut text)));
+                    }
+                    chunks.push(self.read_fstring_code(quote));
+                    continue;
+                },
+                _ => text.push(c),
+            }
+            self.advance();
+        }
+        
+        if !text.is_empty() {
+            chunks.push(FStringChunk::Text(text));
+        }
+        Some(Token::FString(chunks))
+    }
+    
+    // Читает {выражение:спецификация}, текущий символ — '{'
+    fn read_fstring_code(&mut self, quote: char) -> FStringChunk {
+        self.advance(); // Пропускаем '{'
+        
+        let mut code = String::new();
+        let mut spec: Option<String> = None;
+        let mut depth = 0;
+        let mut inner_quote = None;
+        let mut closed = false;
+        while let Some(&c) = self.current() {
+            if c == quote && inner_quote.is_none() {
+                break; // Незакрытая скобка: строка кончилась раньше
+            }
+            self.advance();
+            
+            if let Some(spec) = spec.as_mut() {
+                if c == '}' {
+                    closed = true;
+                    break;
+                }
+                spec.push(c);
+                continue;
+            }
+            
+            match c {
+                '"' | '\'' if inner_quote == Some(c) => inner_quote = None,
+                '"' | '\'' if inner_quote.is_none() => inner_quote = Some(c),
+                _ if inner_quote.is_some() => {},
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' => depth -= 1,
+                '}' if depth > 0 => depth -= 1,
+                '}' => {
+                    closed = true;
+                    break;
+                },
+                ':' if depth == 0 => {
+                    spec = Some(String::new());
+                    continue;
+                },
+                _ => {},
+            }
+            code.push(c);
+        }
+        
+        if !closed {
+            self.error(format!("незакрытая {{ в f-строке: {{{}", code));
+        }
+        let spec = spec.and_then(|spec| match parse_format_spec(&spec) {
+            Ok(format) => Some(format),
+            Err(message) => {
+                self.error(message);
+                None
+            }
+        });
+        FStringChunk::Code(code, spec)
+    }
+    
+    // Оператор или его составное присваивание: + или +=
+    fn read_operator(&mut self, plain: Token, assign: Token) -> Option<Token> {
+        self.advance(); // Пропускаем сам оператор
+        if let Some('=') = self.current() {
+            self.advance();
+            Some(assign)
+        } else {
+            Some(plain)
+        }
+    }
+    
+    // Оператор из одного или двух одинаковых символов, например * и **, с = или без:
+    // [*, *=, **, **=]
+    fn read_doubled_operator(&mut self, symbol: char, [plain, assign, doubled, doubled_assign]: [Token; 4]) -> Option<Token> {
+        if self.peek_next() == Some(&symbol) {
             self.advance();
-            Some(Token::Arrow)
+            self.read_operator(doubled, doubled_assign)
         } else {
-            Some(Token::Minus)
+            self.read_operator(plain, assign)
+        }
+    }
+    
+    fn read_minus_or_arrow(&mut self) -> Option<Token> {
+        self.advance(); // Пропускаем '-'
+        match self.current() {
+            Some('>') => {
+                self.advance();
+                Some(Token::Arrow)
+            },
+            Some('=') => {
+                self.advance();
+                Some(Token::MinusEq)
+            },
+            _ => Some(Token::Minus),
         }
     }
     
@@ -234,24 +649,77 @@ impl Lexer {
             self.next_token()
         }
     }
+}
+
+// Разбирает спецификацию формата; ошибка — текст сообщения без номера строки
+fn parse_format_spec(spec: &str) -> Result<FormatSpec, String> {
+    let chars: Vec<char> = spec.chars().collect();
+    let mut pos = 0;
+    let mut format = FormatSpec {
+        fill: ' ',
+        align: None,
+        sign: None,
+        width: None,
+        precision: None,
+        kind: None,
+    };
+    
+    let is_align = |c: Option<&char>| matches!(c, Some('<') | Some('>') | Some('^') | Some('='));
+    if is_align(chars.get(1)) {
+        format.fill = chars[0];
+        format.align = Some(chars[1]);
+        pos = 2;
+    } else if is_align(chars.first()) {
+        format.align = Some(chars[0]);
+        pos = 1;
+    }
     
-    fn read_less(&mut self) -> Option<Token> {
-        self.advance(); // Пропускаем '<'
-        if let Some('=') = self.current() {
-            self.advance();
-            Some(Token::Le)
-        } else {
-            Some(Token::Lt)
+    if let Some(&c) = chars.get(pos).filter(|c| matches!(c, '+' | '-' | ' ')) {
+        format.sign = Some(c);
+        pos += 1;
+    }
+    
+    if chars.get(pos) == Some(&'#') {
+        return Err(format!("в спецификации формата «{}» флаг # не поддерживается", spec));
+    }
+    
+    // Ведущий ноль — дополнение нулями после знака
+    if chars.get(pos) == Some(&'0') && format.align.is_none() {
+        format.fill = '0';
+        format.align = Some('=');
+        pos += 1;
+    }
+    
+    let digits = |pos: &mut usize| {
+        let start = *pos;
+        while chars.get(*pos).is_some_and(|c| c.is_ascii_digit()) {
+            *pos += 1;
+        }
+        chars[start..*pos].iter().collect::<String>().parse::<usize>().ok()
+    };
+    
+    format.width = digits(&mut pos);
+    if chars.get(pos) == Some(&',') {
+        return Err(format!("в спецификации формата «{}» разделитель тысяч , не поддерживается", spec));
+    }
+    if chars.get(pos) == Some(&'.') {
+        pos += 1;
+        format.precision = digits(&mut pos);
+        if format.precision.is_none() {
+            return Err(format!("в спецификации формата «{}» после точки нужна точность", spec));
         }
     }
     
-    fn read_greater(&mut self) -> Option<Token> {
-        self.advance(); // Пропускаем '>'
-        if let Some('=') = self.current() {
-            self.advance();
-            Some(Token::Ge)
-        } else {
-            Some(Token::Gt)
+    if let Some(&c) = chars.get(pos) {
+        if !"bdoxXeEfFgGs%".contains(c) {
+            return Err(format!("в спецификации формата «{}» неизвестный тип {}", spec, c));
         }
+        format.kind = Some(c);
+        pos += 1;
+    }
+    
+    if pos != chars.len() {
+        return Err(format!("неверная спецификация формата «{}»", spec));
     }
+    Ok(format)
 }
\ No newline at end of file
@@ -1,25 +1,52 @@
 mod lexer;
 mod ast;
 mod parser;
+mod modules;
+mod semantic;
 mod codegen;
+mod stdlib;
 
 use std::env;
 use std::fs;
-use std::process::{self, Command};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
 
-use lexer::Lexer;
-use parser::Parser;
+use ast::Stmt;
+
+use modules::ModuleLoader;
+use semantic::Checker;
 use codegen::CodeGen;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
     
-    if args.len() != 2 {
-        eprintln!("Использование: cvadroscript <файл.cst>");
-        process::exit(1);
+    // Каталоги поиска модулей: сначала из -I, затем из CVADROSCRIPT_PATH.
+    // Аргументы после имени файла передаются программе.
+    let mut search_path = Vec::new();
+    let mut filename = None;
+    // Переполнение целых по умолчанию проверяется; --unchecked-arithmetic отключает проверку
+    let mut checked_arithmetic = true;
+    let mut rest = args.iter().skip(1);
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "-I" => match rest.next() {
+                Some(dir) => search_path.push(PathBuf::from(dir)),
+                None => usage(),
+            },
+            "--checked-arithmetic" => checked_arithmetic = true,
+            "--unchecked-arithmetic" => checked_arithmetic = false,
+            _ => {
+                filename = Some(arg.clone());
+                break;
+            }
+        }
     }
+    let program_args: Vec<&String> = rest.collect();
+    let filename = filename.unwrap_or_else(|| usage());
     
-    let filename = &args[1];
+    if let Some(paths) = env::var_os("CVADROSCRIPT_PATH") {
+        search_path.extend(env::split_paths(&paths));
+    }
     
     // Проверяем расширение файла
     if !filename.ends_with(".cst") {
@@ -27,47 +54,38 @@ fn main() {
         process::exit(1);
     }
     
-    // Читаем исходный код
-    let source = match fs::read_to_string(filename) {
-        Ok(content) => content,
+    println!("Компилируем файл: {}", filename);
+    
+    // Этап 1: Лексический и синтаксический анализ всех модулей
+    println!("Этап 1: Загрузка модулей...");
+    let mut modules = match ModuleLoader::load(Path::new(&filename), search_path) {
+        Ok(modules) => modules,
         Err(err) => {
-            eprintln!("Ошибка чтения файла {}: {}", filename, err);
+            for line in err.lines() {
+                eprintln!("Ошибка: {}", line);
+            }
             process::exit(1);
         }
     };
     
-    println!("Компилируем файл: {}", filename);
+    for module in &modules {
+        let count = module.ast.iter().filter(|stmt| !matches!(stmt, Stmt::Line(_))).count();
+        println!("Модуль {}: AST с {} выражениями", module.name, count);
+    }
     
-    // Этап 1: Лексический анализ
-    println!("Этап 1: Лексический анализ...");
-    let mut lexer = Lexer::new(&source);
-    let mut tokens = Vec::new();
-    
-    loop {
-        match lexer.next_token() {
-            Some(token) => {
-                if token == lexer::Token::EOF {
-                    tokens.push(token);
-                    break;
-                }
-                tokens.push(token);
-            },
-            None => break,
+    // Этап 2: Семантический анализ
+    println!("Этап 2: Семантический анализ...");
+    if let Err(errors) = Checker::check(&mut modules) {
+        for error in &errors {
+            eprintln!("Ошибка: {}", error);
         }
+        eprintln!("Найдено ошибок: {}", errors.len());
+        process::exit(1);
     }
     
-    println!("Найдено {} токенов", tokens.len());
-    
-    // Этап 2: Синтаксический анализ
-    println!("Этап 2: Синтаксический анализ...");
-    let mut parser = Parser::new(tokens);
-    let ast = parser.parse();
-    
-    println!("Создано AST с {} выражениями", ast.len());
-    
     // Этап 3: Генерация кода
     println!("Этап 3: Генерация C++ кода...");
-    let cpp_code = CodeGen::generate(ast);
+    let cpp_code = CodeGen::generate(&modules, checked_arithmetic);
     
     // Создаем временный файл
     let temp_cpp = "temp_cvadroscript.cpp";
@@ -84,7 +102,7 @@ fn main() {
     // Этап 4: Компиляция C++
     println!("Этап 4: Компиляция C++ кода...");
     let compile_result = Command::new("g++")
-        .args(&["-o", temp_exe, temp_cpp])
+        .args(["-o", temp_exe, temp_cpp])
         .output();
     
     match compile_result {
@@ -104,30 +122,51 @@ fn main() {
         }
     }
     
+    // Мост для pyimport кладём рядом с временными файлами
+    let uses_python = modules.iter()
+        .flat_map(|module| &module.ast)
+        .any(|stmt| matches!(stmt, Stmt::PyImport(..)));
+    if uses_python {
+        if let Err(err) = fs::write(TEMP_PY_BRIDGE, PY_BRIDGE) {
+            eprintln!("Ошибка создания временного файла: {}", err);
+            cleanup_files(temp_cpp, temp_exe);
+            process::exit(1);
+        }
+    }
+    let bridge_path = env::current_dir()
+        .map(|dir| dir.join(TEMP_PY_BRIDGE))
+        .unwrap_or_else(|_| PathBuf::from(TEMP_PY_BRIDGE));
+    
     // Этап 5: Выполнение программы
     println!("Этап 5: Выполнение программы...");
     println!("================================");
     
-    let run_result = if cfg!(target_os = "windows") {
-        Command::new(format!(".\\{}", temp_exe)).output()
+    let mut program = if cfg!(target_os = "windows") {
+        Command::new(format!(".\\{}", temp_exe))
     } else {
-        Command::new(format!("./{}", temp_exe)).output()
+        Command::new(format!("./{}", temp_exe))
     };
-    
+    // Программа работает с терминалом напрямую: читает стандартный ввод
+    // и выводит результат по мере выполнения
+    let run_result = program
+        .args(&program_args)
+        .env("CVADROSCRIPT_PY_BRIDGE", &bridge_path)
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status();
+    
+    let mut exit_code = 0;
     match run_result {
-        Ok(output) => {
-            // Выводим результат выполнения
-            print!("{}", String::from_utf8_lossy(&output.stdout));
-            if !output.stderr.is_empty() {
-                eprint!("{}", String::from_utf8_lossy(&output.stderr));
-            }
-            
-            if !output.status.success() {
-                eprintln!("Программа завершилась с ошибкой (код: {:?})", output.status.code());
+        Ok(status) => {
+            if !status.success() {
+                eprintln!("Программа завершилась с ошибкой (код: {:?})", status.code());
+                exit_code = status.code().unwrap_or(1);
             }
         },
         Err(err) => {
             eprintln!("Ошибка выполнения программы: {}", err);
+            exit_code = 1;
         }
     }
     
@@ -136,9 +175,23 @@ fn main() {
     // Очистка временных файлов
     cleanup_files(temp_cpp, temp_exe);
     println!("Выполнение завершено!");
+    if exit_code != 0 {
+        process::exit(exit_code);
+    }
+}
+
+const PY_BRIDGE: &str = include_str!("../imports/_import_.py");
+const TEMP_PY_BRIDGE: &str = "temp_cvadroscript_import.py";
+
+fn usage() -> ! {
+    eprintln!("Использование: cvadroscript [-I <каталог>]... [--checked-arithmetic | --unchecked-arithmetic] <файл.cst> [аргумент]...");
+    eprintln!("  --checked-arithmetic    переполнение целых вызывает OverflowError (по умолчанию)");
+    eprintln!("  --unchecked-arithmetic  без проверки переполнения");
+    process::exit(1);
 }
 
 fn cleanup_files(cpp_file: &str, exe_file: &str) {
     let _ = fs::remove_file(cpp_file);
     let _ = fs::remove_file(exe_file);
+    let _ = fs::remove_file(TEMP_PY_BRIDGE);
 }
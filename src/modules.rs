@@ -0,0 +1,129 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::ast::Stmt;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+use crate::stdlib::is_std_module;
+
+pub struct Module {
+    pub name: String,
+    pub path: PathBuf,
+    pub ast: Vec<Stmt>,
+}
+
+impl Module {
+    // Имена модулей, подключённых через import и from ... import
+    pub fn imports(&self) -> Vec<&str> {
+        self.ast.iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::Import(module) | Stmt::FromImport(module, _) => Some(module.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+pub struct ModuleLoader {
+    search_path: Vec<PathBuf>,
+    // Модули в порядке зависимостей: каждый идёт после всех, кого он импортирует
+    modules: Vec<Module>,
+    // Стек загружаемых сейчас файлов для обнаружения циклических импортов
+    loading: Vec<(String, PathBuf)>,
+}
+
+impl ModuleLoader {
+    // Загружает файл и все импортируемые им модули. Главный модуль будет последним.
+    pub fn load(entry: &Path, search_path: Vec<PathBuf>) -> Result<Vec<Module>, String> {
+        let mut loader = ModuleLoader {
+            search_path,
+            modules: Vec::new(),
+            loading: Vec::new(),
+        };
+        let name = entry.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        loader.load_module(&name, entry)?;
+        Ok(loader.modules)
+    }
+    
+    fn load_module(&mut self, name: &str, path: &Path) -> Result<(), String> {
+        let canonical = fs::canonicalize(path)
+            .map_err(|err| format!("не удалось прочитать файл {}: {}", path.display(), err))?;
+        
+        if let Some(pos) = self.loading.iter().position(|(_, loading)| loading == &canonical) {
+            let mut cycle: Vec<&str> = self.loading[pos..].iter().map(|(name, _)| name.as_str()).collect();
+            cycle.push(name);
+            return Err(format!("циклический импорт: {}", cycle.join(" -> ")));
+        }
+        
+        if let Some(module) = self.modules.iter().find(|module| module.name == name) {
+            if module.path == canonical {
+                return Ok(());
+            }
+            return Err(format!(
+                "модуль {} найден в двух местах: {} и {}",
+                name, module.path.display(), canonical.display()
+            ));
+        }
+        
+        let source = fs::read_to_string(&canonical)
+            .map_err(|err| format!("не удалось прочитать файл {}: {}", path.display(), err))?;
+        let ast = parse_source(&source).map_err(|errors| {
+            // Ошибки импортируемого модуля помечаются его именем, как в семантическом анализе
+            let is_entry = self.loading.is_empty();
+            errors.iter()
+                .map(|error| if is_entry { error.clone() } else { format!("модуль {}, {}", name, error) })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })?;
+        let module = Module {
+            name: name.to_string(),
+            path: canonical.clone(),
+            ast,
+        };
+        
+        // Импорты ищем относительно каталога импортирующего файла
+        let base_dir = canonical.parent().map(Path::to_path_buf).unwrap_or_default();
+        self.loading.push((name.to_string(), canonical));
+        for import in module.imports() {
+            // Стандартные модули встроены в компилятор, их имена зарезервированы
+            if is_std_module(import) {
+                continue;
+            }
+            let import_path = self.resolve(import, &base_dir)?;
+            self.load_module(import, &import_path)?;
+        }
+        self.loading.pop();
+        
+        self.modules.push(module);
+        Ok(())
+    }
+    
+    fn resolve(&self, name: &str, base_dir: &Path) -> Result<PathBuf, String> {
+        let file_name = format!("{}.cst", name);
+        let dirs: Vec<&Path> = std::iter::once(base_dir)
+            .chain(self.search_path.iter().map(PathBuf::as_path))
+            .collect();
+        
+        for dir in &dirs {
+            let candidate = dir.join(&file_name);
+            if candidate.is_file() {
+                return Ok(candidate);
+            }
+        }
+        
+        let searched = dirs.iter()
+            .map(|dir| dir.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        Err(format!("модуль {} не найден (искали в: {})", name, searched))
+    }
+}
+
+// Ошибки лексического анализа возвращаются все сразу, по одной на строку
+pub fn parse_source(source: &str) -> Result<Vec<Stmt>, Vec<String>> {
+    let (tokens, lines) = Lexer::new(source).tokenize()?;
+    let mut parser = Parser::new(tokens, lines);
+    Ok(parser.parse())
+}
@@ -1,15 +1,17 @@
-use crate::lexer::{Token};
-use crate::ast::{Stmt, Expr, FunctionDef, Block, Type, Literal, BinOp, UnaryOp};
+use crate::lexer::{Lexer, Token, FStringChunk};
+use crate::ast::{Stmt, Expr, FunctionDef, StructDef, Block, Type, Literal, BinOp, UnaryOp, FStringPart, ExceptClause, Param};
 
 pub struct Parser {
     tokens: Vec<Token>,
+    lines: Vec<usize>,
     current: usize,
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>) -> Self {
+    pub fn new(tokens: Vec<Token>, lines: Vec<usize>) -> Self {
         Parser {
             tokens,
+            lines,
             current: 0,
         }
     }
@@ -22,6 +24,7 @@ impl Parser {
                 self.advance();
             }
             if !self.is_at_end() {
+                program.push(Stmt::Line(self.line()));
                 program.push(self.declaration());
             }
         }
@@ -31,22 +34,55 @@ impl Parser {
     fn declaration(&mut self) -> Stmt {
         match self.peek() {
             Token::Def => self.function(),
-            Token::Let => self.let_stmt(),
+            Token::Struct => self.struct_def(),
+            Token::Import | Token::From => self.import(),
+            Token::PyImport => self.py_import(),
+            Token::Let | Token::Const => self.let_stmt(),
             _ => self.statement(),
         }
     }
     
     fn function(&mut self) -> Stmt {
+        let mut function = self.signature();
+        
+        self.consume(&Token::Colon);
+        self.block_start();
+        
+        function.body = self.block();
+        Stmt::Function(function)
+    }
+    
+    // def name[T](a: int, b: T) -> T — заголовок функции без тела
+    fn signature(&mut self) -> FunctionDef {
         self.consume(&Token::Def);
         let name = self.consume_ident();
+        let type_params = self.type_params();
         self.consume(&Token::LParen);
         
         let mut params = vec![];
         while self.peek() != &Token::RParen {
+            // *args: int собирает лишние позиционные аргументы в list[int]
+            let variadic = self.peek() == &Token::Star;
+            if variadic {
+                self.advance();
+            }
             let name = self.consume_ident();
             self.consume(&Token::Colon);
-            let type_ = self.parse_type();
-            params.push((name, type_));
+            let mut ty = self.parse_type();
+            if variadic {
+                ty = Type::List(Box::new(ty));
+            }
+            
+            let default = if self.peek() == &Token::Eq {
+                if variadic {
+                    panic!("Variadic parameter {} cannot have a default value", name);
+                }
+                self.advance();
+                Some(self.expression())
+            } else {
+                None
+            };
+            params.push(Param { name, ty, default, variadic });
             
             if self.peek() == &Token::Comma {
                 self.advance();
@@ -57,28 +93,116 @@ impl Parser {
         self.consume(&Token::Arrow);
         let return_type = self.parse_type();
         
+        FunctionDef {
+            name,
+            type_params,
+            params,
+            return_type,
+            body: Block(vec![]),
+        }
+    }
+    
+    // pyimport textutils:
+    //     def shout(text: string) -> string
+    fn py_import(&mut self) -> Stmt {
+        self.consume(&Token::PyImport);
+        let module = self.consume_ident();
         self.consume(&Token::Colon);
+        self.block_start();
         
-        // Пропускаем newline и ожидаем indent для тела функции
-        if self.peek() == &Token::Newline {
-            self.advance();
+        let mut functions = vec![];
+        while self.peek() != &Token::Dedent && !self.is_at_end() {
+            functions.push(self.signature());
+            if self.peek() == &Token::Newline {
+                self.advance();
+            }
         }
-        if self.peek() == &Token::Indent {
+        
+        if self.peek() == &Token::Dedent {
             self.advance();
         }
         
-        let body = self.block();
+        Stmt::PyImport(module, functions)
+    }
+    
+    fn struct_def(&mut self) -> Stmt {
+        self.consume(&Token::Struct);
+        let name = self.consume_ident();
+        let type_params = self.type_params();
+        self.consume(&Token::Colon);
+        self.block_start();
+        
+        let mut fields = vec![];
+        while self.peek() != &Token::Dedent && !self.is_at_end() {
+            let field = self.consume_ident();
+            self.consume(&Token::Colon);
+            let type_ = self.parse_type();
+            fields.push((field, type_));
+            
+            if self.peek() == &Token::Newline {
+                self.advance();
+            }
+        }
+        
+        if self.peek() == &Token::Dedent {
+            self.advance();
+        }
         
-        Stmt::Function(FunctionDef {
+        Stmt::Struct(StructDef {
             name,
-            params,
-            return_type,
-            body,
+            type_params,
+            fields,
         })
     }
     
+    // import utils
+    // from utils import f, Point
+    fn import(&mut self) -> Stmt {
+        let stmt = if self.peek() == &Token::Import {
+            self.advance();
+            Stmt::Import(self.consume_ident())
+        } else {
+            self.consume(&Token::From);
+            let module = self.consume_ident();
+            self.consume(&Token::Import);
+            
+            let mut names = vec![self.consume_ident()];
+            while self.peek() == &Token::Comma {
+                self.advance();
+                names.push(self.consume_ident());
+            }
+            Stmt::FromImport(module, names)
+        };
+        
+        if self.peek() == &Token::Newline {
+            self.advance();
+        }
+        
+        stmt
+    }
+    
+    // Необязательный список параметров типа: [T, U]
+    fn type_params(&mut self) -> Vec<String> {
+        let mut type_params = vec![];
+        if self.peek() != &Token::LBracket {
+            return type_params;
+        }
+        
+        self.advance();
+        while self.peek() != &Token::RBracket {
+            type_params.push(self.consume_ident());
+            if self.peek() == &Token::Comma {
+                self.advance();
+            }
+        }
+        self.consume(&Token::RBracket);
+        
+        type_params
+    }
+    
     fn let_stmt(&mut self) -> Stmt {
-        self.consume(&Token::Let);
+        let is_const = self.peek() == &Token::Const;
+        self.advance();
         let name = self.consume_ident();
         
         let type_annotation = if self.peek() == &Token::Colon {
@@ -96,14 +220,18 @@ impl Parser {
             self.advance();
         }
         
-        Stmt::Let(name, type_annotation, expr)
+        if is_const {
+            Stmt::Const(name, type_annotation, expr)
+        } else {
+            Stmt::Let(name, type_annotation, expr)
+        }
     }
     
     fn statement(&mut self) -> Stmt {
         match self.peek() {
             Token::Return => {
                 self.advance();
-                let expr = if self.peek() != &Token::Newline {
+                let expr = if !matches!(self.peek(), Token::Newline | Token::Dedent | Token::EOF) {
                     Some(self.expression())
                 } else {
                     None
@@ -114,18 +242,55 @@ impl Parser {
                 self.advance();
                 Stmt::Pass
             },
+            Token::Raise => {
+                self.advance();
+                let expr = if !matches!(self.peek(), Token::Newline | Token::Dedent | Token::EOF) {
+                    Some(self.expression())
+                } else {
+                    None
+                };
+                Stmt::Raise(expr)
+            },
+            Token::Try => self.try_stmt(),
+            Token::Global => {
+                self.advance();
+                let mut names = vec![self.consume_ident()];
+                while self.peek() == &Token::Comma {
+                    self.advance();
+                    names.push(self.consume_ident());
+                }
+                Stmt::Global(names)
+            },
             Token::If => self.if_stmt(),
             Token::While => self.while_stmt(),
             Token::For => self.for_stmt(),
             _ => {
-                // Проверяем, не является ли это присваиванием
-                if self.is_assignment() {
-                    let name = self.consume_ident();
-                    self.consume(&Token::Eq);
-                    let expr = self.expression();
-                    Stmt::Assign(name, expr)
-                } else {
-                    Stmt::Expr(self.expression())
+                let expr = self.expression();
+                
+                // Левая часть присваивания разбирается как обычное выражение:
+                // x = 1, a[i] = 2, p.x += 3. Допустима ли она, проверяет семантический анализ.
+                let op = match self.peek() {
+                    Token::Eq => None,
+                    Token::PlusEq => Some(BinOp::Add),
+                    Token::MinusEq => Some(BinOp::Sub),
+                    Token::StarEq => Some(BinOp::Mul),
+                    Token::SlashEq => Some(BinOp::Div),
+                    Token::PercentEq => Some(BinOp::Mod),
+                    Token::StarStarEq => Some(BinOp::Pow),
+                    Token::SlashSlashEq => Some(BinOp::FloorDiv),
+                    Token::AmpEq => Some(BinOp::BitAnd),
+                    Token::PipeEq => Some(BinOp::BitOr),
+                    Token::CaretEq => Some(BinOp::BitXor),
+                    Token::ShlEq => Some(BinOp::Shl),
+                    Token::ShrEq => Some(BinOp::Shr),
+                    _ => return Stmt::Expr(expr),
+                };
+                self.advance();
+                let value = self.expression();
+                
+                match op {
+                    Some(op) => Stmt::AugAssign(expr, op, value),
+                    None => Stmt::Assign(expr, value),
                 }
             }
         }
@@ -135,15 +300,13 @@ impl Parser {
         self.consume(&Token::If);
         let condition = self.expression();
         self.consume(&Token::Colon);
-        self.consume(&Token::Newline);
-        self.consume(&Token::Indent);
+        self.block_start();
         let then_block = self.block();
         
         let else_block = if self.peek() == &Token::Else {
             self.advance();
             self.consume(&Token::Colon);
-            self.consume(&Token::Newline);
-            self.consume(&Token::Indent);
+            self.block_start();
             Some(self.block())
         } else {
             None
@@ -156,8 +319,7 @@ impl Parser {
         self.consume(&Token::While);
         let condition = self.expression();
         self.consume(&Token::Colon);
-        self.consume(&Token::Newline);
-        self.consume(&Token::Indent);
+        self.block_start();
         let body = self.block();
         
         Stmt::Expr(Expr::While(Box::new(condition), body))
@@ -169,17 +331,65 @@ impl Parser {
         self.consume(&Token::In);
         let iterable = self.expression();
         self.consume(&Token::Colon);
-        self.consume(&Token::Newline);
-        self.consume(&Token::Indent);
+        self.block_start();
         let body = self.block();
         
         Stmt::Expr(Expr::For(var, Box::new(iterable), body))
     }
     
+    fn try_stmt(&mut self) -> Stmt {
+        self.consume(&Token::Try);
+        self.consume(&Token::Colon);
+        self.block_start();
+        let body = self.block();
+        
+        let mut handlers = vec![];
+        while self.peek() == &Token::Except {
+            self.advance();
+            let error_type = match self.peek() {
+                Token::Ident(_) => Some(self.consume_ident()),
+                _ => None,
+            };
+            let name = if error_type.is_some() && self.peek() == &Token::As {
+                self.advance();
+                Some(self.consume_ident())
+            } else {
+                None
+            };
+            self.consume(&Token::Colon);
+            self.block_start();
+            handlers.push(ExceptClause { error_type, name, body: self.block() });
+        }
+        
+        let finally = if self.peek() == &Token::Finally {
+            self.advance();
+            self.consume(&Token::Colon);
+            self.block_start();
+            Some(self.block())
+        } else {
+            None
+        };
+        
+        if handlers.is_empty() && finally.is_none() {
+            panic!("Expected except or finally after try block");
+        }
+        
+        Stmt::Try(body, handlers, finally)
+    }
+    
+    // После ':' лексер сразу выдаёт Indent, но допускаем и Newline перед ним
+    fn block_start(&mut self) {
+        if self.peek() == &Token::Newline {
+            self.advance();
+        }
+        self.consume(&Token::Indent);
+    }
+    
     fn block(&mut self) -> Block {
         let mut stmts = vec![];
         
         while self.peek() != &Token::Dedent && !self.is_at_end() {
+            stmts.push(Stmt::Line(self.line()));
             stmts.push(self.declaration());
             if self.peek() == &Token::Newline {
                 self.advance();
@@ -194,7 +404,37 @@ impl Parser {
     }
     
     fn expression(&mut self) -> Expr {
-        self.logical_or()
+        if self.peek() == &Token::Lambda {
+            return self.lambda();
+        }
+        
+        // Условное выражение: x if cond else y
+        let expr = self.logical_or();
+        if self.peek() == &Token::If {
+            self.advance();
+            let condition = self.logical_or();
+            self.consume(&Token::Else);
+            let else_expr = self.expression();
+            return Expr::Conditional(Box::new(condition), Box::new(expr), Box::new(else_expr));
+        }
+        expr
+    }
+    
+    fn lambda(&mut self) -> Expr {
+        self.consume(&Token::Lambda);
+        
+        let mut params = vec![];
+        while self.peek() != &Token::Colon {
+            params.push(self.consume_ident());
+            if self.peek() == &Token::Comma {
+                self.advance();
+            }
+        }
+        
+        self.consume(&Token::Colon);
+        let body = self.expression();
+        
+        Expr::Lambda(params, Box::new(body))
     }
     
     fn logical_or(&mut self) -> Expr {
@@ -210,43 +450,96 @@ impl Parser {
     }
     
     fn logical_and(&mut self) -> Expr {
-        let mut expr = self.equality();
+        let mut expr = self.comparison();
         
         while self.peek() == &Token::And {
             self.advance();
-            let right = self.equality();
+            let right = self.comparison();
             expr = Expr::BinaryOp(Box::new(expr), BinOp::And, Box::new(right));
         }
         
         expr
     }
     
-    fn equality(&mut self) -> Expr {
-        let mut expr = self.comparison();
+    // Сравнения образуют цепочку, как в Python: a < b <= c означает a < b and b <= c,
+    // а b вычисляется один раз. Одиночное сравнение остаётся бинарной операцией.
+    fn comparison(&mut self) -> Expr {
+        let first = self.bit_or();
+        let mut rest = vec![];
         
-        while matches!(self.peek(), Token::EqEq | Token::Ne) {
+        loop {
             let op = match self.peek() {
                 Token::EqEq => BinOp::Eq,
                 Token::Ne => BinOp::Ne,
-                _ => unreachable!(),
+                Token::Gt => BinOp::Gt,
+                Token::Ge => BinOp::Ge,
+                Token::Lt => BinOp::Lt,
+                Token::Le => BinOp::Le,
+                Token::In => BinOp::In,
+                Token::Not if self.peek_at(1) == &Token::In => {
+                    self.advance();
+                    BinOp::NotIn
+                },
+                _ => break,
             };
             self.advance();
-            let right = self.comparison();
-            expr = Expr::BinaryOp(Box::new(expr), op, Box::new(right));
+            rest.push((op, self.bit_or()));
+        }
+        
+        match rest.len() {
+            0 => first,
+            1 => {
+                let (op, right) = rest.pop().unwrap();
+                Expr::BinaryOp(Box::new(first), op, Box::new(right))
+            },
+            _ => Expr::Compare(Box::new(first), rest),
+        }
+    }
+    
+    // Побитовые операции связывают слабее арифметики, но сильнее сравнений, как в Python
+    fn bit_or(&mut self) -> Expr {
+        let mut expr = self.bit_xor();
+        
+        while self.peek() == &Token::Pipe {
+            self.advance();
+            let right = self.bit_xor();
+            expr = Expr::BinaryOp(Box::new(expr), BinOp::BitOr, Box::new(right));
         }
         
         expr
     }
     
-    fn comparison(&mut self) -> Expr {
+    fn bit_xor(&mut self) -> Expr {
+        let mut expr = self.bit_and();
+        
+        while self.peek() == &Token::Caret {
+            self.advance();
+            let right = self.bit_and();
+            expr = Expr::BinaryOp(Box::new(expr), BinOp::BitXor, Box::new(right));
+        }
+        
+        expr
+    }
+    
+    fn bit_and(&mut self) -> Expr {
+        let mut expr = self.shift();
+        
+        while self.peek() == &Token::Amp {
+            self.advance();
+            let right = self.shift();
+            expr = Expr::BinaryOp(Box::new(expr), BinOp::BitAnd, Box::new(right));
+        }
+        
+        expr
+    }
+    
+    fn shift(&mut self) -> Expr {
         let mut expr = self.term();
         
-        while matches!(self.peek(), Token::Gt | Token::Ge | Token::Lt | Token::Le) {
+        while matches!(self.peek(), Token::Shl | Token::Shr) {
             let op = match self.peek() {
-                Token::Gt => BinOp::Gt,
-                Token::Ge => BinOp::Ge,
-                Token::Lt => BinOp::Lt,
-                Token::Le => BinOp::Le,
+                Token::Shl => BinOp::Shl,
+                Token::Shr => BinOp::Shr,
                 _ => unreachable!(),
             };
             self.advance();
@@ -275,23 +568,37 @@ impl Parser {
     }
     
     fn factor(&mut self) -> Expr {
-        let mut expr = self.unary();
+        let mut expr = self.cast();
         
-        while matches!(self.peek(), Token::Star | Token::Slash | Token::Percent) {
+        while matches!(self.peek(), Token::Star | Token::Slash | Token::SlashSlash | Token::Percent) {
             let op = match self.peek() {
                 Token::Star => BinOp::Mul,
                 Token::Slash => BinOp::Div,
+                Token::SlashSlash => BinOp::FloorDiv,
                 Token::Percent => BinOp::Mod,
                 _ => unreachable!(),
             };
             self.advance();
-            let right = self.unary();
+            let right = self.cast();
             expr = Expr::BinaryOp(Box::new(expr), op, Box::new(right));
         }
         
         expr
     }
     
+    // as связывает сильнее умножения, но слабее унарных операций: -x as u8 == (-x) as u8
+    fn cast(&mut self) -> Expr {
+        let mut expr = self.unary();
+        
+        while self.peek() == &Token::As {
+            self.advance();
+            let ty = self.parse_type();
+            expr = Expr::Cast(Box::new(expr), ty);
+        }
+        
+        expr
+    }
+    
     fn unary(&mut self) -> Expr {
         match self.peek() {
             Token::Not => {
@@ -302,28 +609,85 @@ impl Parser {
                 self.advance();
                 Expr::UnaryOp(UnaryOp::Neg, Box::new(self.unary()))
             },
-            _ => self.call(),
+            Token::Tilde => {
+                self.advance();
+                Expr::UnaryOp(UnaryOp::BitNot, Box::new(self.unary()))
+            },
+            _ => self.power(),
         }
     }
     
+    // ** правоассоциативна и связывает сильнее унарного минуса слева: -2 ** 2 == -4,
+    // но справа допускает его: 2 ** -1
+    fn power(&mut self) -> Expr {
+        let expr = self.call();
+        
+        if self.peek() == &Token::StarStar {
+            self.advance();
+            let right = self.unary();
+            return Expr::BinaryOp(Box::new(expr), BinOp::Pow, Box::new(right));
+        }
+        
+        expr
+    }
+    
     fn call(&mut self) -> Expr {
         let mut expr = self.primary();
         
-        while self.peek() == &Token::LParen {
-            self.advance();
-            let mut args = vec![];
-            
-            while self.peek() != &Token::RParen {
-                args.push(self.expression());
-                if self.peek() == &Token::Comma {
+        loop {
+            match self.peek() {
+                Token::LParen => {
                     self.advance();
-                }
-            }
-            
-            self.consume(&Token::RParen);
-            
-            if let Expr::Variable(name) = expr {
-                expr = Expr::Call(name, args);
+                    let mut args = vec![];
+                    let mut kwargs = vec![];
+                    
+                    while self.peek() != &Token::RParen {
+                        if matches!(self.peek(), Token::Ident(_)) && self.peek_at(1) == &Token::Eq {
+                            let name = self.consume_ident();
+                            self.advance();
+                            kwargs.push((name, self.expression()));
+                        } else if kwargs.is_empty() {
+                            args.push(self.expression());
+                        } else {
+                            panic!("Positional argument after keyword argument");
+                        }
+                        if self.peek() == &Token::Comma {
+                            self.advance();
+                        }
+                    }
+                    
+                    self.consume(&Token::RParen);
+                    
+                    // Вызывать можно любое выражение: имя функции, лямбду, результат другого вызова
+                    expr = Expr::Call(Box::new(expr), args, kwargs);
+                },
+                Token::Dot => {
+                    self.advance();
+                    let field = self.consume_ident();
+                    expr = Expr::Field(Box::new(expr), field);
+                },
+                Token::LBracket => {
+                    self.advance();
+                    let start = if self.peek() == &Token::Colon {
+                        None
+                    } else {
+                        Some(Box::new(self.expression()))
+                    };
+                    if self.peek() == &Token::Colon {
+                        self.advance();
+                        let end = if self.peek() == &Token::RBracket {
+                            None
+                        } else {
+                            Some(Box::new(self.expression()))
+                        };
+                        self.consume(&Token::RBracket);
+                        expr = Expr::Slice(Box::new(expr), start, end);
+                    } else {
+                        self.consume(&Token::RBracket);
+                        expr = Expr::Index(Box::new(expr), start.unwrap());
+                    }
+                },
+                _ => break,
             }
         }
         
@@ -345,21 +709,38 @@ impl Parser {
                 self.advance();
                 Expr::Literal(Literal::Bool(false))
             },
-            Token::Int(n) => {
-                let n = *n;
+            Token::None => {
+                self.advance();
+                Expr::Literal(Literal::None)
+            },
+            Token::Int(n, ty) => {
+                let (n, ty) = (*n, ty.clone());
                 self.advance();
-                Expr::Literal(Literal::Int(n))
+                suffixed(Expr::Literal(Literal::Int(n)), ty)
             },
-            Token::Float(f) => {
-                let f = *f;
+            Token::Float(f, ty) => {
+                let (f, ty) = (*f, ty.clone());
                 self.advance();
-                Expr::Literal(Literal::Float(f))
+                suffixed(Expr::Literal(Literal::Float(f)), ty)
             },
             Token::String(s) => {
                 let s = s.clone();
                 self.advance();
                 Expr::Literal(Literal::String(s))
             },
+            Token::FString(chunks) => {
+                let parts = chunks.iter()
+                    .map(|chunk| match chunk {
+                        FStringChunk::Text(text) => FStringPart::Text(text.clone()),
+                        FStringChunk::Code(code, spec) => FStringPart::Expr(
+                            parse_embedded(code),
+                            spec.clone(),
+                        ),
+                    })
+                    .collect();
+                self.advance();
+                Expr::FString(parts)
+            },
             Token::Ident(name) => {
                 let name = name.clone();
                 self.advance();
@@ -371,21 +752,73 @@ impl Parser {
                 self.consume(&Token::RParen);
                 expr
             },
+            Token::LBracket => {
+                self.advance();
+                let mut items = vec![];
+                while self.peek() != &Token::RBracket {
+                    items.push(self.expression());
+                    if self.peek() == &Token::Comma {
+                        self.advance();
+                    }
+                }
+                self.consume(&Token::RBracket);
+                Expr::List(items)
+            },
             _ => panic!("Unexpected token: {:?}", self.peek()),
         }
     }
     
     fn parse_type(&mut self) -> Type {
+        let mut ty = self.base_type();
+        while self.peek() == &Token::Question {
+            self.advance();
+            ty = Type::Optional(Box::new(ty));
+        }
+        ty
+    }
+    
+    fn base_type(&mut self) -> Type {
         match self.peek() {
             Token::Ident(type_name) => {
-                let type_name = type_name.clone();
+                let mut type_name = type_name.clone();
                 self.advance();
+                
+                // Тип из другого модуля: utils.Point
+                if self.peek() == &Token::Dot {
+                    self.advance();
+                    type_name = format!("{}.{}", type_name, self.consume_ident());
+                }
+                
+                if let Some(ty) = Type::numeric(&type_name) {
+                    return ty;
+                }
                 match type_name.as_str() {
-                    "int" => Type::Int,
-                    "float" => Type::Float,
+                    "char" => Type::Char,
                     "string" => Type::String,
                     "bool" => Type::Bool,
                     "void" => Type::Void,
+                    "fn" => self.function_type(),
+                    "list" if self.peek() == &Token::LBracket => {
+                        self.advance();
+                        let item = self.parse_type();
+                        self.consume(&Token::RBracket);
+                        Type::List(Box::new(item))
+                    },
+                    _ if self.peek() == &Token::LBracket => {
+                        self.advance();
+                        let mut args = vec![];
+                        while self.peek() != &Token::RBracket {
+                            args.push(self.parse_type());
+                            if self.peek() == &Token::Comma {
+                                self.advance();
+                            }
+                        }
+                        self.consume(&Token::RBracket);
+                        if type_name == "Optional" && args.len() == 1 {
+                            return Type::Optional(Box::new(args.remove(0)));
+                        }
+                        Type::Generic(type_name, args)
+                    },
                     _ => Type::Custom(type_name),
                 }
             },
@@ -393,11 +826,39 @@ impl Parser {
         }
     }
     
+    // fn(int, string) -> bool
+    fn function_type(&mut self) -> Type {
+        self.consume(&Token::LParen);
+        
+        let mut params = vec![];
+        while self.peek() != &Token::RParen {
+            params.push(self.parse_type());
+            if self.peek() == &Token::Comma {
+                self.advance();
+            }
+        }
+        
+        self.consume(&Token::RParen);
+        self.consume(&Token::Arrow);
+        let return_type = self.parse_type();
+        
+        Type::Function(params, Box::new(return_type))
+    }
+    
     // Вспомогательные методы
     fn is_at_end(&self) -> bool {
         self.current >= self.tokens.len() || self.peek() == &Token::EOF
     }
     
+    // Номер строки текущего токена
+    fn line(&self) -> usize {
+        self.lines.get(self.current).copied().unwrap_or(0)
+    }
+    
+    fn peek_at(&self, offset: usize) -> &Token {
+        self.tokens.get(self.current + offset).unwrap_or(&Token::EOF)
+    }
+    
     fn peek(&self) -> &Token {
         self.tokens.get(self.current).unwrap_or(&Token::EOF)
     }
@@ -431,13 +892,28 @@ impl Parser {
             _ => panic!("Expected identifier"),
         }
     }
-    
-    fn is_assignment(&self) -> bool {
-        if let Token::Ident(_) = self.peek() {
-            if let Some(Token::Eq) = self.tokens.get(self.current + 1) {
-                return true;
-            }
-        }
-        false
+}
+
+// Литерал с суффиксом типа равнозначен приведению: 200u8 — то же, что 200 as u8
+fn suffixed(literal: Expr, ty: Option<Type>) -> Expr {
+    match ty {
+        Some(ty) => Expr::Cast(Box::new(literal), ty),
+        None => literal,
+    }
+}
+
+// Выражение внутри фигурных скобок f-строки
+fn parse_embedded(code: &str) -> Expr {
+    let (tokens, lines) = Lexer::new(code.trim())
+        .tokenize()
+        .unwrap_or_else(|errors| panic!("Invalid literal in f-string expression {{{}}}: {}", code, errors.join("; ")));
+    if tokens.len() == 1 {
+        panic!("Empty expression in f-string");
+    }
+    let mut parser = Parser::new(tokens, lines);
+    let expr = parser.expression();
+    if !parser.is_at_end() {
+        panic!("Unexpected token in f-string: {:?}", parser.peek());
     }
+    expr
 }
\ No newline at end of file
@@ -0,0 +1,2161 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ast::{Stmt, Expr, FunctionDef, StructDef, Block, Type, Literal, BinOp, UnaryOp, FStringPart, ExceptClause, Param};
+use crate::modules::Module;
+use crate::stdlib::{STD_MODULES, std_module};
+
+// Встроенные исключения; у каждого есть поле message
+const EXCEPTION_TYPES: &[&str] = &[
+    "Error", "ValueError", "IndexError", "KeyError", "TypeError",
+    "ZeroDivisionError", "OverflowError", "IOError", "EOFError", "RuntimeError",
+];
+
+// Типы аргументов вызова
+struct Call<'a> {
+    args: &'a [Option<Type>],
+    kwargs: &'a [(String, Option<Type>)],
+    // Сами аргументы: лямбды проверяются, когда известен тип параметра
+    exprs: &'a [Expr],
+    kwarg_exprs: &'a [(String, Expr)],
+}
+
+pub struct Checker {
+    // Все структуры программы по полному имени (utils.Point у импортируемых модулей,
+    // просто Point у главного); типы полей тоже записаны полными именами
+    structs: HashMap<String, StructDef>,
+    // Функции верхнего уровня каждого уже проверенного модуля
+    exports: HashMap<String, HashMap<String, FunctionDef>>,
+    // Глобальные переменные и константы каждого уже проверенного модуля
+    global_exports: HashMap<String, HashMap<String, Type>>,
+    
+    // Состояние проверяемого модуля
+    module: String,
+    is_entry: bool,
+    imported: Vec<String>,
+    struct_names: HashMap<String, String>,
+    functions: HashMap<String, FunctionDef>,
+    module_exports: HashMap<String, FunctionDef>,
+    py_modules: HashMap<String, HashMap<String, FunctionDef>>,
+    // Переменные и константы верхнего уровня; тип None, если его не удалось вывести
+    global_types: HashMap<String, Option<Type>>,
+    // Выведенные типы локальных переменных без аннотации по строке и имени
+    local_types: HashMap<(usize, String), Type>,
+    // Строковые литералы из одного символа, которые стоят на месте char, по адресу в AST модуля
+    char_literals: HashSet<*const Expr>,
+    // Имена верхнего уровня, которые нельзя изменять: константы и импортированные переменные
+    constants: Vec<String>,
+    // Глобальные переменные, объявленные в текущей функции через global
+    global_names: Vec<String>,
+    // Тип переменной может быть неизвестен (параметры лямбд, переменные цикла)
+    scopes: Vec<HashMap<String, Option<Type>>>,
+    // Вложенные функции вместе с номером области видимости, где они объявлены
+    local_functions: Vec<(usize, FunctionDef)>,
+    // Вложенные функции, объявленные ниже в охватывающих блоках: ссылаться на них ещё нельзя
+    later_functions: Vec<String>,
+    // Номер области видимости параметров текущей функции
+    function_scope: usize,
+    type_params: Vec<String>,
+    current_function: Option<String>,
+    // Объявленный тип результата текущей функции
+    return_type: Option<Type>,
+    // Ожидаемый тип лямбды, которую сейчас проверяет infer (см. infer_expecting)
+    lambda_type: Option<Type>,
+    // Вложенность блоков except и finally в текущей функции
+    except_depth: usize,
+    finally_depth: usize,
+    // Строка проверяемой инструкции для сообщений об ошибках
+    line: usize,
+    errors: Vec<String>,
+}
+
+impl Checker {
+    // Модули проверяются в порядке зависимостей, главный — последним.
+    // Выведенные типы переменных верхнего уровня записываются в их объявления.
+    pub fn check(modules: &mut [Module]) -> Result<(), Vec<String>> {
+        let mut checker = Checker {
+            structs: HashMap::new(),
+            exports: HashMap::new(),
+            global_exports: HashMap::new(),
+            module: String::new(),
+            is_entry: false,
+            imported: Vec::new(),
+            struct_names: HashMap::new(),
+            functions: HashMap::new(),
+            module_exports: HashMap::new(),
+            py_modules: HashMap::new(),
+            global_types: HashMap::new(),
+            local_types: HashMap::new(),
+            char_literals: HashSet::new(),
+            constants: Vec::new(),
+            global_names: Vec::new(),
+            scopes: Vec::new(),
+            local_functions: Vec::new(),
+            later_functions: Vec::new(),
+            function_scope: 0,
+            type_params: Vec::new(),
+            current_function: None,
+            return_type: None,
+            lambda_type: None,
+            except_depth: 0,
+            finally_depth: 0,
+            line: 0,
+            errors: Vec::new(),
+        };
+        
+        for name in EXCEPTION_TYPES {
+            checker.structs.insert(name.to_string(), StructDef {
+                name: name.to_string(),
+                type_params: Vec::new(),
+                fields: vec![("message".to_string(), Type::String)],
+            });
+        }
+        
+        // Стандартные модули доступны так же, как уже проверенные модули программы
+        for name in STD_MODULES {
+            let functions = std_module(name).unwrap_or_default().into_iter()
+                .map(|f| (f.name.clone(), f))
+                .collect();
+            checker.exports.insert(name.to_string(), functions);
+        }
+        
+        let count = modules.len();
+        for (i, module) in modules.iter_mut().enumerate() {
+            checker.check_module(module, i + 1 == count);
+        }
+        
+        if checker.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(checker.errors)
+        }
+    }
+    
+    fn check_module(&mut self, module: &mut Module, is_entry: bool) {
+        self.module = module.name.clone();
+        self.is_entry = is_entry;
+        self.imported.clear();
+        self.struct_names = EXCEPTION_TYPES.iter()
+            .map(|name| (name.to_string(), name.to_string()))
+            .collect();
+        self.functions.clear();
+        self.module_exports.clear();
+        self.py_modules.clear();
+        self.global_types.clear();
+        self.local_types.clear();
+        self.char_literals.clear();
+        self.constants.clear();
+        self.scopes = vec![HashMap::new()];
+        
+        // Инструкции верхнего уровня вместе с номерами их строк
+        let mut line = 0;
+        let mut stmts = Vec::new();
+        for stmt in &module.ast {
+            match stmt {
+                Stmt::Line(n) => line = *n,
+                _ => stmts.push((line, stmt)),
+            }
+        }
+        
+        // Импорты, структуры и сигнатуры функций видны во всём модуле независимо
+        // от порядка объявления, поэтому регистрируются до проверки остального кода
+        for &(line, stmt) in &stmts {
+            self.line = line;
+            if let Stmt::Import(_) | Stmt::FromImport(..) | Stmt::PyImport(..) = stmt {
+                self.check_stmt(stmt);
+            }
+        }
+        for &(line, stmt) in &stmts {
+            self.line = line;
+            if let Stmt::Struct(def) = stmt {
+                self.declare_struct(def);
+            }
+        }
+        for &(line, stmt) in &stmts {
+            self.line = line;
+            match stmt {
+                Stmt::Struct(def) => self.check_struct(def),
+                Stmt::Function(func) => {
+                    if self.functions.contains_key(&func.name) {
+                        self.error(format!("функция {} уже объявлена", func.name));
+                    }
+                    if is_entry && func.name == "main" {
+                        self.check_main(func);
+                    }
+                    self.register_function(func);
+                },
+                _ => {}
+            }
+        }
+        
+        for &(line, stmt) in &stmts {
+            self.line = line;
+            match stmt {
+                Stmt::Function(_) | Stmt::Struct(_) | Stmt::Import(_) | Stmt::FromImport(..) | Stmt::PyImport(..) => {},
+                Stmt::Let(..) | Stmt::Const(..) => self.check_stmt(stmt),
+                _ => {
+                    if !is_entry {
+                        self.error("на верхнем уровне импортируемого модуля допускаются только объявления".to_string());
+                    }
+                    self.check_stmt(stmt);
+                }
+            }
+        }
+        
+        // Тела функций проверяются последними: к моменту их вызова все глобальные
+        // переменные уже объявлены. Сужение типов на верхнем уровне на них не действует.
+        for (name, ty) in &self.global_types {
+            self.scopes[0].insert(name.clone(), ty.clone());
+        }
+        for &(line, stmt) in &stmts {
+            self.line = line;
+            if let Stmt::Function(func) = stmt {
+                if let Some(signature) = self.functions.get(&func.name).cloned() {
+                    self.check_function_body(func, &signature);
+                }
+            }
+        }
+        
+        let exports = std::mem::take(&mut self.module_exports);
+        self.exports.insert(module.name.clone(), exports);
+        
+        // Генератору кода нужен тип каждой глобальной переменной, а локальные
+        // переменные с известным типом он объявляет с этим типом вместо auto
+        for stmt in &mut module.ast {
+            if let Stmt::Let(name, ty @ None, _) | Stmt::Const(name, ty @ None, _) = stmt {
+                *ty = self.global_types.get(name.as_str()).cloned().flatten();
+            }
+        }
+        annotate_locals(&mut module.ast, &self.local_types);
+        convert_char_literals(&mut module.ast, &self.char_literals);
+        let globals = self.global_types.iter()
+            .filter_map(|(name, ty)| Some((name.clone(), ty.clone()?)))
+            .collect();
+        self.global_exports.insert(module.name.clone(), globals);
+    }
+    
+    fn check_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expr(expr) => {
+                self.infer(expr);
+                
+                // if x == None: return — дальше в блоке x уже не None
+                if let Expr::If(condition, then_block, _) = expr {
+                    if let Some((name, ty, false)) = self.narrowing(condition) {
+                        if then_block.always_exits() {
+                            self.declare(&name, Some(ty));
+                        }
+                    }
+                }
+            },
+            Stmt::Let(name, type_annotation, expr) => {
+                if !self.is_top_level() {
+                    self.check_redeclaration(name);
+                }
+                let ty = self.check_let(name, type_annotation.as_ref(), expr);
+                if self.is_top_level() {
+                    if !self.is_entry && !expr.is_literal() {
+                        self.error(format!("глобальная переменная {} модуля должна инициализироваться литералом", name));
+                    }
+                    self.declare_global(name, ty.clone());
+                } else if let (None, Some(ty)) = (type_annotation, &ty) {
+                    self.local_types.insert((self.line, name.clone()), ty.clone());
+                }
+                self.declare(name, ty);
+            },
+            Stmt::Const(name, type_annotation, expr) => {
+                if !self.is_top_level() {
+                    self.error("const допускается только на верхнем уровне модуля".to_string());
+                }
+                if !self.is_const_expr(expr) {
+                    self.error(format!("значение константы {} должно вычисляться при компиляции", name));
+                }
+                let ty = self.check_let(name, type_annotation.as_ref(), expr);
+                if let Some(ty) = &ty {
+                    if !(ty.is_numeric() || matches!(ty, Type::Char | Type::Bool | Type::String)) {
+                        self.error(format!("константа {} не может иметь тип {}", name, ty));
+                    }
+                }
+                self.declare_global(name, ty.clone());
+                self.constants.push(name.clone());
+                self.declare(name, ty);
+            },
+            Stmt::Global(names) => {
+                if self.current_function.is_none() {
+                    self.error("global допускается только внутри функции".to_string());
+                    return;
+                }
+                for name in names {
+                    if self.constants.contains(name) {
+                        self.error(format!("нельзя изменить константу {}", name));
+                    } else if !self.global_types.contains_key(name) {
+                        self.error(format!("глобальная переменная {} не объявлена", name));
+                    } else {
+                        self.global_names.push(name.clone());
+                    }
+                }
+            },
+            Stmt::Assign(target, expr) => {
+                let expected = self.check_target(target);
+                let actual = match &expected {
+                    Some(expected) => self.infer_expecting(expr, expected),
+                    None => self.infer(expr),
+                };
+                if let (Some(expected), Some(actual)) = (expected, actual) {
+                    self.check_value(&expected, &actual, expr, "присваивание");
+                }
+            },
+            Stmt::AugAssign(target, op, expr) => {
+                let actual = self.infer(expr);
+                if let (Some(expected), Some(actual)) = (self.check_target(target), actual) {
+                    let what = format!("присваивание {}=", op);
+                    match arithmetic_type(op, &expected, &actual) {
+                        // Как в C++, x += y не расширяет тип x: для u8 результат u8 + u8 снова u8
+                        Some(_) if expected.is_integer() && actual.is_integer() => {
+                            if !matches!(op, BinOp::Shl | BinOp::Shr) {
+                                self.check_value(&expected, &actual, expr, &what);
+                            }
+                        },
+                        Some(result) => self.check_assignable(&expected, &result, &what),
+                        None => self.error(format!("оператор {}= неприменим к типам {} и {}", op, expected, actual)),
+                    }
+                }
+            },
+            Stmt::Function(func) => {
+                self.check_nested_function(func);
+            },
+            Stmt::Struct(def) => {
+                self.declare_struct(def);
+                self.check_struct(def);
+            },
+            Stmt::Import(module) => {
+                if self.check_import_position() && !self.imported.contains(module) {
+                    self.imported.push(module.clone());
+                }
+            },
+            Stmt::FromImport(module, names) => {
+                if self.check_import_position() {
+                    for name in names {
+                        self.import_name(module, name);
+                    }
+                }
+            },
+            Stmt::PyImport(module, functions) => {
+                if self.check_import_position() {
+                    self.check_py_import(module, functions);
+                }
+            },
+            Stmt::Return(expr) => {
+                if self.finally_depth > 0 {
+                    self.error("return внутри finally не поддерживается".to_string());
+                }
+                let expected = self.return_type.clone();
+                let actual = match (expr, &expected) {
+                    (Some(expr), Some(expected)) => self.infer_expecting(expr, expected),
+                    (Some(expr), None) => self.infer(expr),
+                    (None, _) => None,
+                };
+                let Some(expected) = expected else {
+                    return;
+                };
+                match (expr, expected) {
+                    (Some(_), Type::Void) => {
+                        self.error("return со значением в функции, которая ничего не возвращает".to_string());
+                    },
+                    (Some(expr), expected) => {
+                        // int? вместо int: подсказываем проверить значение на None
+                        let optional = matches!(actual, Some(Type::Optional(_)));
+                        if optional && !matches!(expected, Type::Optional(_)) {
+                            self.check_present(&actual);
+                        } else if let Some(actual) = actual {
+                            self.check_value(&expected, &actual, expr, "возвращаемое значение");
+                        }
+                    },
+                    (None, Type::Void) => {},
+                    (None, expected) => {
+                        self.error(format!("return без значения: функция должна вернуть значение типа {}", expected));
+                    },
+                }
+            },
+            Stmt::Raise(Some(expr)) => {
+                match self.infer(expr) {
+                    Some(Type::Custom(name)) if EXCEPTION_TYPES.contains(&name.as_str()) => {},
+                    Some(ty) => self.error(format!("raise ожидает исключение, получен тип {}", ty)),
+                    None => {}
+                }
+            },
+            Stmt::Raise(None) => {
+                if self.except_depth == 0 {
+                    self.error("raise без исключения допускается только внутри except".to_string());
+                }
+            },
+            Stmt::Try(body, handlers, finally) => {
+                self.check_block(body);
+                for handler in handlers {
+                    self.check_handler(handler);
+                }
+                if let Some(finally) = finally {
+                    self.finally_depth += 1;
+                    self.check_block(finally);
+                    self.finally_depth -= 1;
+                }
+            },
+            Stmt::Pass => {},
+            Stmt::Line(line) => {
+                self.line = *line;
+            }
+        }
+    }
+    
+    // В C++ второе объявление в том же блоке — ошибка, даже с тем же типом.
+    // Параметры функции находятся в одном блоке с её телом. Повторные глобальные
+    // имена проверяет declare_global.
+    fn check_redeclaration(&mut self, name: &str) {
+        let depth = self.scopes.len() - 1;
+        let declared = self.scopes[depth].contains_key(name);
+        let is_param = self.current_function.is_some() && depth == self.function_scope + 1
+            && self.scopes[self.function_scope].contains_key(name);
+        if declared {
+            self.error(format!("переменная {} уже объявлена в этом блоке: для нового значения используйте {} = ...", name, name));
+        } else if is_param {
+            self.error(format!("{} — параметр функции: для нового значения используйте {} = ...", name, name));
+        }
+    }
+    
+    // Проверяет объявление let или const и возвращает тип переменной
+    fn check_let(&mut self, name: &str, type_annotation: Option<&Type>, expr: &Expr) -> Option<Type> {
+        if let Some(expected) = type_annotation {
+            if !self.check_type(expected) {
+                self.infer(expr);
+                return None;
+            }
+            let expected = self.canonicalize(expected);
+            if let Some(actual) = self.infer_expecting(expr, &expected) {
+                self.check_value(&expected, &actual, expr, &format!("переменная {}", name));
+            }
+            return Some(expected);
+        }
+        
+        let actual = self.infer(expr);
+        if actual == Some(Type::None) {
+            self.error(format!("тип переменной {} нельзя вывести из None, укажите его: let {}: int? = None", name, name));
+            None
+        } else if matches!(expr, Expr::List(items) if items.is_empty()) {
+            self.error(format!("тип переменной {} нельзя вывести из пустого списка, укажите его: let {}: list[int] = []", name, name));
+            None
+        } else {
+            actual
+        }
+    }
+    
+    fn is_top_level(&self) -> bool {
+        self.current_function.is_none() && self.scopes.len() == 1
+    }
+    
+    fn declare_global(&mut self, name: &str, ty: Option<Type>) {
+        if self.global_types.insert(name.to_string(), ty).is_some() || self.constants.iter().any(|c| c == name) {
+            self.error(format!("глобальное имя {} уже объявлено", name));
+        }
+    }
+    
+    // Константу можно вычислить при компиляции C++: литералы, другие константы и операции над ними.
+    // //, % и ** над дробными числами вызывают std::floor, std::fmod и std::pow, а операции
+    // над std::string, кроме сцепления в строковую константу, не бывают constexpr.
+    fn is_const_expr(&mut self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Literal(_) => true,
+            Expr::Variable(name) => self.constants.contains(name) && self.scope_depth(name) == Some(0),
+            Expr::UnaryOp(_, operand) | Expr::Cast(operand, _) => self.is_const_expr(operand),
+            Expr::BinaryOp(_, BinOp::In | BinOp::NotIn, _) => false,
+            Expr::BinaryOp(left, op, right) => {
+                if !(self.is_const_expr(left) && self.is_const_expr(right)) {
+                    return false;
+                }
+                let (left_type, right_type) = (self.infer(left), self.infer(right));
+                let left_type = self.adapt_literal(left, left_type, &right_type);
+                let right_type = self.adapt_literal(right, right_type, &left_type);
+                let types = [left_type, right_type];
+                let is_integer = |ty: &Option<Type>| ty.as_ref().is_some_and(Type::is_integer);
+                match op {
+                    BinOp::FloorDiv | BinOp::Mod | BinOp::Pow => types.iter().all(is_integer),
+                    BinOp::Add => true,
+                    _ => !types.contains(&Some(Type::String)),
+                }
+            },
+            Expr::Conditional(condition, then_expr, else_expr) => {
+                self.is_const_expr(condition) && self.is_const_expr(then_expr) && self.is_const_expr(else_expr)
+            },
+            _ => false,
+        }
+    }
+    
+    fn check_handler(&mut self, handler: &ExceptClause) {
+        self.scopes.push(HashMap::new());
+        if let Some(error_type) = &handler.error_type {
+            if !EXCEPTION_TYPES.contains(&error_type.as_str()) {
+                self.error(format!("{} не является типом исключения", error_type));
+            }
+            if let Some(name) = &handler.name {
+                self.declare(name, Some(Type::Custom(error_type.clone())));
+            }
+        }
+        
+        self.except_depth += 1;
+        self.check_block(&handler.body);
+        self.except_depth -= 1;
+        self.scopes.pop();
+    }
+    
+    fn check_import_position(&mut self) -> bool {
+        if self.current_function.is_some() || self.scopes.len() > 1 {
+            self.error("import допускается только на верхнем уровне модуля".to_string());
+            return false;
+        }
+        true
+    }
+    
+    // from module import name
+    fn import_name(&mut self, module: &str, name: &str) {
+        if name.starts_with('_') {
+            self.error(format!("имя {} модуля {} является приватным", name, module));
+            return;
+        }
+        
+        let qualified = format!("{}.{}", module, name);
+        if let Some(f) = self.exports.get(module).and_then(|exports| exports.get(name)) {
+            self.functions.insert(name.to_string(), f.clone());
+        } else if self.structs.contains_key(&qualified) {
+            self.struct_names.insert(name.to_string(), qualified);
+        } else if let Some(ty) = self.global_exports.get(module).and_then(|globals| globals.get(name)).cloned() {
+            // Импортированную переменную можно читать, но не изменять
+            self.declare(name, Some(ty));
+            self.constants.push(name.to_string());
+        } else {
+            self.error(format!("в модуле {} нет имени {}", module, name));
+        }
+    }
+    
+    // Функции модуля Python вызываются через мост, который умеет передавать
+    // только скалярные значения
+    fn check_py_import(&mut self, module: &str, functions: &[FunctionDef]) {
+        if self.imported.iter().any(|imported| imported == module) || self.exports.contains_key(module) {
+            self.error(format!("имя модуля {} уже занято", module));
+            return;
+        }
+        
+        let mut exports = HashMap::new();
+        for f in functions {
+            if !f.type_params.is_empty() {
+                self.error(format!("функция Python {}.{} не может быть обобщённой", module, f.name));
+            }
+            let is_scalar = |ty: &Type| matches!(ty, Type::Int | Type::Float | Type::String | Type::Bool);
+            self.check_params(f);
+            let unsupported = f.params.iter()
+                .map(|param| &param.ty)
+                .filter(|ty| !is_scalar(ty))
+                .chain(Some(&f.return_type).filter(|ty| !is_scalar(ty) && **ty != Type::Void));
+            for ty in unsupported.cloned().collect::<Vec<_>>() {
+                self.error(format!(
+                    "функция Python {}.{}: тип {} не поддерживается, допустимы int, float, string, bool",
+                    module, f.name, ty
+                ));
+            }
+            if exports.insert(f.name.clone(), f.clone()).is_some() {
+                self.error(format!("функция Python {}.{} объявлена дважды", module, f.name));
+            }
+        }
+        
+        self.imported.push(module.to_string());
+        self.py_modules.insert(module.to_string(), exports);
+    }
+    
+    // Функции модуля, импортированного текущим модулем (CvadroScript или Python)
+    fn module_functions(&self, module: &str) -> Option<&HashMap<String, FunctionDef>> {
+        self.py_modules.get(module).or_else(|| self.exports.get(module))
+    }
+    
+    // Проверяет сигнатуру и регистрирует функцию
+    fn register_function(&mut self, f: &FunctionDef) {
+        let signature = self.check_signature(f);
+        self.module_exports.insert(f.name.clone(), signature.clone());
+        self.functions.insert(f.name.clone(), signature);
+    }
+    
+    // Сигнатура хранится с полными именами типов, чтобы её можно было экспортировать
+    fn check_signature(&mut self, f: &FunctionDef) -> FunctionDef {
+        let outer_type_params = self.type_params.clone();
+        let outer_function = self.current_function.replace(f.name.clone());
+        self.declare_type_params(&f.type_params);
+        
+        for param in &f.params {
+            self.check_type(&param.ty);
+        }
+        self.check_type(&f.return_type);
+        self.check_params(f);
+        // Явно указать параметры типа при вызове нельзя: они выводятся только из аргументов
+        for name in &f.type_params {
+            if !f.params.iter().any(|param| mentions_any(&param.ty, std::slice::from_ref(name))) {
+                self.error(format!("нельзя вывести параметр типа {}: он не встречается в параметрах функции", name));
+            }
+        }
+        
+        let mut signature = f.clone();
+        signature.params = f.params.iter()
+            .map(|param| Param { ty: self.canonicalize(&param.ty), ..param.clone() })
+            .collect();
+        signature.return_type = self.canonicalize(&f.return_type);
+        signature.body = Block(Vec::new());
+        
+        self.type_params = outer_type_params;
+        self.current_function = outer_function;
+        signature
+    }
+    
+    // Вложенная функция видна с места объявления до конца блока. Переменные внешних
+    // функций она захватывает по значению, как лямбда, поэтому изменять их не может.
+    fn check_nested_function(&mut self, f: &FunctionDef) {
+        if !f.type_params.is_empty() {
+            self.error(format!("вложенная функция {} не может быть обобщённой", f.name));
+        }
+        let signature = self.check_signature(f);
+        self.declare(&f.name, Some(signature.function_type()));
+        self.local_functions.push((self.scopes.len() - 1, signature.clone()));
+        self.check_function_body(f, &signature);
+    }
+    
+    fn check_function_body(&mut self, f: &FunctionDef, signature: &FunctionDef) {
+        let outer_type_params = self.type_params.clone();
+        let outer_function = self.current_function.replace(f.name.clone());
+        let outer_scope = std::mem::replace(&mut self.function_scope, self.scopes.len());
+        let outer_except = std::mem::take(&mut self.except_depth);
+        let outer_finally = std::mem::take(&mut self.finally_depth);
+        let outer_globals = std::mem::take(&mut self.global_names);
+        let outer_return = self.return_type.replace(signature.return_type.clone());
+        self.type_params.extend(f.type_params.iter().cloned());
+        
+        self.scopes.push(HashMap::new());
+        for param in &signature.params {
+            self.declare(&param.name, Some(param.ty.clone()));
+        }
+        self.check_block(&f.body);
+        self.scopes.pop();
+        
+        self.type_params = outer_type_params;
+        self.current_function = outer_function;
+        self.return_type = outer_return;
+        self.function_scope = outer_scope;
+        self.except_depth = outer_except;
+        self.finally_depth = outer_finally;
+        self.global_names = outer_globals;
+    }
+    
+    // main вызывается после инструкций верхнего уровня; int, который она вернёт,
+    // становится кодом возврата программы
+    fn check_main(&mut self, f: &FunctionDef) {
+        let args_type = Type::List(Box::new(Type::String));
+        let valid_params = match f.params.as_slice() {
+            [] => true,
+            [param] => param.ty == args_type && !param.variadic && param.default.is_none(),
+            _ => false,
+        };
+        let valid_return = matches!(f.return_type, Type::Int | Type::Void);
+        if !valid_params || !valid_return || !f.type_params.is_empty() {
+            self.error(
+                "функция main должна иметь вид def main() -> int или def main(args: list[string]) -> int \
+                 (вместо int допускается void)".to_string()
+            );
+        }
+    }
+    
+    // Порядок параметров как в Python: обязательные, со значением по умолчанию, *args
+    fn check_params(&mut self, f: &FunctionDef) {
+        let mut seen_default = false;
+        for (i, param) in f.params.iter().enumerate() {
+            if f.params[..i].iter().any(|other| other.name == param.name) {
+                self.error(format!("параметр {} объявлен дважды", param.name));
+            }
+            if param.variadic {
+                if i + 1 != f.params.len() {
+                    self.error(format!("параметр *{} должен быть последним", param.name));
+                }
+                continue;
+            }
+            
+            match &param.default {
+                Some(default) => {
+                    seen_default = true;
+                    // Значение подставляется в место вызова, поэтому допускаются только литералы
+                    if !default.is_literal() {
+                        self.error(format!("значение по умолчанию параметра {} должно быть константой", param.name));
+                    } else if let Some(actual) = self.infer(default) {
+                        let expected = self.canonicalize(&param.ty);
+                        self.check_value(&expected, &actual, default, &format!("значение по умолчанию параметра {}", param.name));
+                    }
+                },
+                None if seen_default => {
+                    self.error(format!(
+                        "параметр {} без значения по умолчанию идёт после параметра со значением по умолчанию",
+                        param.name
+                    ));
+                },
+                None => {}
+            }
+        }
+    }
+    
+    // Регистрирует имя структуры, чтобы на неё можно было ссылаться до объявления
+    fn declare_struct(&mut self, s: &StructDef) {
+        let qualified = self.qualified_struct(&s.name);
+        if self.struct_names.contains_key(&s.name) {
+            self.error(format!("структура {} уже объявлена", s.name));
+        }
+        self.struct_names.insert(s.name.clone(), qualified.clone());
+        self.structs.insert(qualified.clone(), StructDef {
+            name: qualified,
+            type_params: s.type_params.clone(),
+            fields: Vec::new(),
+        });
+    }
+    
+    fn qualified_struct(&self, name: &str) -> String {
+        if self.is_entry {
+            name.to_string()
+        } else {
+            format!("{}.{}", self.module, name)
+        }
+    }
+    
+    fn check_struct(&mut self, s: &StructDef) {
+        let qualified = self.qualified_struct(&s.name);
+        
+        let outer_type_params = self.type_params.clone();
+        self.declare_type_params(&s.type_params);
+        
+        let mut seen = Vec::new();
+        let mut fields = Vec::new();
+        for (name, ty) in &s.fields {
+            if seen.contains(name) {
+                self.error(format!("поле {} структуры {} объявлено дважды", name, s.name));
+            }
+            seen.push(name.clone());
+            self.check_type(ty);
+            fields.push((name.clone(), self.canonicalize(ty)));
+        }
+        
+        self.type_params = outer_type_params;
+        self.structs.insert(qualified.clone(), StructDef {
+            name: qualified,
+            type_params: s.type_params.clone(),
+            fields,
+        });
+    }
+    
+    fn declare_type_params(&mut self, type_params: &[String]) {
+        for (i, name) in type_params.iter().enumerate() {
+            if type_params[..i].contains(name) {
+                self.error(format!("параметр типа {} объявлен дважды", name));
+            }
+            if self.struct_names.contains_key(name) {
+                self.error(format!("параметр типа {} совпадает с именем структуры", name));
+            }
+            self.type_params.push(name.clone());
+        }
+    }
+    
+    fn check_block(&mut self, block: &Block) {
+        self.scopes.push(HashMap::new());
+        let outer_later = self.later_functions.len();
+        self.later_functions.extend(block.0.iter().filter_map(|stmt| match stmt {
+            Stmt::Function(f) => Some(f.name.clone()),
+            _ => None,
+        }));
+        for stmt in &block.0 {
+            if let Stmt::Function(f) = stmt {
+                if let Some(pos) = self.later_functions[outer_later..].iter().position(|name| *name == f.name) {
+                    self.later_functions.remove(outer_later + pos);
+                }
+            }
+            self.check_stmt(stmt);
+        }
+        self.later_functions.truncate(outer_later);
+        self.scopes.pop();
+    }
+    
+    // Проверяет, что все имена в типе объявлены и параметры типа указаны верно
+    fn check_type(&mut self, ty: &Type) -> bool {
+        match ty {
+            Type::Custom(name) => {
+                if self.type_params.contains(name) {
+                    return true;
+                }
+                match self.resolve_struct(name) {
+                    Ok(qualified) => {
+                        let count = self.structs[&qualified].type_params.len();
+                        if count > 0 {
+                            self.error(format!("тип {} требует параметров типа: {}", name, count));
+                            return false;
+                        }
+                        true
+                    },
+                    Err(message) => {
+                        self.error(message);
+                        false
+                    }
+                }
+            },
+            Type::Generic(name, args) => {
+                let mut valid = if self.type_params.contains(name) {
+                    self.error(format!("параметр типа {} не может иметь параметров", name));
+                    false
+                } else {
+                    match self.resolve_struct(name) {
+                        Ok(qualified) => {
+                            let count = self.structs[&qualified].type_params.len();
+                            if count != args.len() {
+                                self.error(format!(
+                                    "тип {} ожидает параметров типа: {}, указано: {}",
+                                    name, count, args.len()
+                                ));
+                            }
+                            count == args.len()
+                        },
+                        Err(message) => {
+                            self.error(message);
+                            false
+                        }
+                    }
+                };
+                for arg in args {
+                    valid &= self.check_type(arg);
+                }
+                valid
+            },
+            Type::List(item) => self.check_type(item),
+            Type::Optional(inner) => {
+                if matches!(inner.as_ref(), Type::Optional(_) | Type::Void) {
+                    self.error(format!("тип {} не может быть опциональным", inner));
+                    return false;
+                }
+                self.check_type(inner)
+            },
+            Type::Function(params, return_type) => {
+                let mut valid = self.check_type(return_type);
+                for param in params {
+                    valid &= self.check_type(param);
+                }
+                valid
+            },
+            Type::Int | Type::Float | Type::I8 | Type::I16 | Type::I32 | Type::U8 | Type::U16 | Type::U32
+                | Type::U64 | Type::F32 | Type::Char | Type::String | Type::Bool | Type::Void | Type::None => true,
+        }
+    }
+    
+    // Находит полное имя структуры: Point, импортированный Point или utils.Point
+    fn resolve_struct(&self, name: &str) -> Result<String, String> {
+        if let Some((module, local)) = name.split_once('.') {
+            if !self.imported.iter().any(|imported| imported == module) {
+                return Err(format!("модуль {} не импортирован", module));
+            }
+            if local.starts_with('_') {
+                return Err(format!("имя {} модуля {} является приватным", local, module));
+            }
+            if !self.structs.contains_key(name) {
+                return Err(format!("в модуле {} нет типа {}", module, local));
+            }
+            return Ok(name.to_string());
+        }
+        
+        self.struct_names.get(name)
+            .cloned()
+            .ok_or_else(|| format!("неизвестный тип {}", name))
+    }
+    
+    // Заменяет имена структур полными, чтобы типы из разных модулей можно было сравнивать
+    fn canonicalize(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Custom(name) if !self.type_params.contains(name) => {
+                Type::Custom(self.resolve_struct(name).unwrap_or_else(|_| name.clone()))
+            },
+            Type::Generic(name, args) => Type::Generic(
+                self.resolve_struct(name).unwrap_or_else(|_| name.clone()),
+                args.iter().map(|arg| self.canonicalize(arg)).collect(),
+            ),
+            Type::List(item) => Type::List(Box::new(self.canonicalize(item))),
+            Type::Optional(inner) => Type::Optional(Box::new(self.canonicalize(inner))),
+            Type::Function(params, return_type) => Type::Function(
+                params.iter().map(|param| self.canonicalize(param)).collect(),
+                Box::new(self.canonicalize(return_type)),
+            ),
+            _ => ty.clone(),
+        }
+    }
+    
+    // Выводит тип выражения; None означает, что тип определить не удалось
+    fn infer(&mut self, expr: &Expr) -> Option<Type> {
+        match expr {
+            Expr::Literal(lit) => Some(match lit {
+                Literal::Int(_) => Type::Int,
+                Literal::Float(_) => Type::Float,
+                Literal::String(_) => Type::String,
+                Literal::Bool(_) => Type::Bool,
+                Literal::None => Type::None,
+            }),
+            Expr::Variable(name) => {
+                if let Some(ty) = self.lookup_scope(name) {
+                    // Переменная верхнего уровня без известного типа остаётся локальной в main
+                    let unknown_global = ty.is_none() && self.scope_depth(name) == Some(0)
+                        && self.global_types.contains_key(name);
+                    if unknown_global && self.current_function.is_some() {
+                        self.error(format!(
+                            "тип глобальной переменной {} неизвестен: укажите его, чтобы использовать переменную в функциях",
+                            name
+                        ));
+                    }
+                    return ty;
+                }
+                match self.functions.get(name) {
+                    Some(f) if f.type_params.is_empty() => Some(f.function_type()),
+                    Some(_) => None,
+                    None => {
+                        if self.struct_names.contains_key(name) {
+                            self.error(format!("структура {} не является значением: создайте экземпляр {}(...)", name, name));
+                        } else if self.imported.contains(name) {
+                            self.error(format!("модуль {} не является значением", name));
+                        } else if self.later_functions.contains(name) {
+                            // Вложенная функция в C++ — переменная, поэтому до объявления её нет
+                            self.error(format!(
+                                "вложенная функция {} объявлена ниже: вложенные функции могут вызывать только объявленные до них",
+                                name
+                            ));
+                        } else if name != "print" && builtin_function(name).is_none() {
+                            self.error(format!("имя {} не объявлено", name));
+                        }
+                        None
+                    }
+                }
+            },
+            Expr::BinaryOp(left_expr, op, right_expr) => {
+                let left = self.infer(left_expr);
+                let right = self.infer(right_expr);
+                // Целый литерал принимает тип другого операнда: в x + 1 при x: u8 литерал 1 — u8.
+                // Тип сдвига и степени задаёт левый операнд, поэтому там литералы не подстраиваются.
+                let (left, right) = if matches!(op, BinOp::Shl | BinOp::Shr | BinOp::Pow) {
+                    (left, right)
+                } else {
+                    let left = self.adapt_literal(left_expr, left, &right);
+                    let right = self.adapt_literal(right_expr, right, &left);
+                    (left, right)
+                };
+                match op {
+                    BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Gt | BinOp::Le | BinOp::Ge | BinOp::In | BinOp::NotIn => {
+                        self.check_comparison(op, &left, &right);
+                        Some(Type::Bool)
+                    },
+                    BinOp::And | BinOp::Or => Some(Type::Bool),
+                    _ => {
+                        if !self.check_present(&left) || !self.check_present(&right) {
+                            return None;
+                        }
+                        let (left, right) = (left?, right?);
+                        let result = arithmetic_type(op, &left, &right);
+                        // С параметрами типа операцию проверит компилятор C++
+                        let generic = mentions_any(&left, &self.type_params) || mentions_any(&right, &self.type_params);
+                        if result.is_none() && !generic {
+                            self.error(format!("оператор {} неприменим к типам {} и {}", op, left, right));
+                        }
+                        result
+                    },
+                }
+            },
+            Expr::Compare(first, rest) => {
+                let mut left_expr = first.as_ref();
+                let mut left = self.infer(first);
+                for (op, operand) in rest {
+                    let right = self.infer(operand);
+                    let right = self.adapt_literal(operand, right, &left);
+                    let left_type = self.adapt_literal(left_expr, left.clone(), &right);
+                    self.check_comparison(op, &left_type, &right);
+                    left_expr = operand;
+                    left = right;
+                }
+                Some(Type::Bool)
+            },
+            Expr::Cast(operand, target) => {
+                let actual = self.infer(operand);
+                if !self.check_type(target) {
+                    return None;
+                }
+                let target = self.canonicalize(target);
+                if let Some(actual) = actual {
+                    let valid = actual == target
+                        || (actual.is_numeric() && target.is_numeric())
+                        || (actual.is_integer() && target == Type::Char)
+                        || (actual == Type::Char && target.is_integer())
+                        || (actual == Type::Bool && target.is_numeric());
+                    if !valid {
+                        self.error(format!("нельзя привести значение типа {} к типу {}", actual, target));
+                    }
+                }
+                Some(target)
+            },
+            Expr::Conditional(condition, then_expr, else_expr) => {
+                self.infer(condition);
+                let narrowed = self.narrowing(condition);
+                let then_type = self.infer_narrowed(then_expr, narrowed.as_ref(), true);
+                let else_type = self.infer_narrowed(else_expr, narrowed.as_ref(), false);
+                
+                match (then_type?, else_type?) {
+                    (then_type, else_type) if then_type == else_type => Some(then_type),
+                    (then_type, else_type) if assignable(&then_type, &else_type) => Some(then_type),
+                    (then_type, else_type) if assignable(&else_type, &then_type) => Some(else_type),
+                    (Type::None, Type::Optional(inner)) | (Type::Optional(inner), Type::None) => Some(Type::Optional(inner)),
+                    (Type::None, other) | (other, Type::None) => Some(Type::Optional(Box::new(other))),
+                    (Type::Optional(inner), other) | (other, Type::Optional(inner)) if *inner == other => {
+                        Some(Type::Optional(inner))
+                    },
+                    (then_type, else_type) => {
+                        self.error(format!(
+                            "ветви условного выражения имеют разные типы: {} и {}",
+                            then_type, else_type
+                        ));
+                        None
+                    }
+                }
+            },
+            Expr::UnaryOp(op, operand) => {
+                let ty = self.infer(operand);
+                match op {
+                    UnaryOp::Not => Some(Type::Bool),
+                    UnaryOp::Neg => {
+                        self.check_present(&ty);
+                        match ty {
+                            Some(ty) if ty.is_unsigned() => {
+                                self.error(format!("унарный минус неприменим к беззнаковому типу {}", ty));
+                                None
+                            },
+                            Some(ty) if ty.is_integer() => Some(promote(&ty)),
+                            _ => ty,
+                        }
+                    },
+                    UnaryOp::BitNot => {
+                        self.check_present(&ty);
+                        match ty {
+                            Some(ty) if ty.is_integer() => Some(promote(&ty)),
+                            None => None,
+                            Some(other) => {
+                                self.error(format!("оператор ~ неприменим к типу {}", other));
+                                None
+                            }
+                        }
+                    },
+                }
+            },
+            Expr::Call(callee, args, kwargs) => {
+                let arg_types: Vec<Option<Type>> = args.iter().map(|arg| self.infer(arg)).collect();
+                let kwarg_types: Vec<(String, Option<Type>)> = kwargs.iter()
+                    .map(|(name, arg)| (name.clone(), self.infer(arg)))
+                    .collect();
+                let call = Call {
+                    args: &arg_types,
+                    kwargs: &kwarg_types,
+                    exprs: args,
+                    kwarg_exprs: kwargs,
+                };
+                
+                if let Expr::Variable(name) = callee.as_ref() {
+                    if let Some(f) = self.local_function(name).cloned() {
+                        return self.check_call(&f, &call);
+                    }
+                    if self.lookup_scope(name).is_none() {
+                        if let Some(def) = self.struct_names.get(name).map(|qualified| self.structs[qualified].clone()) {
+                            return self.check_constructor(&def, &call);
+                        }
+                        if let Some(f) = self.functions.get(name).cloned() {
+                            return self.check_call(&f, &call);
+                        }
+                        if name == "print" {
+                            return self.check_print(&call);
+                        }
+                        if let Some(signature) = builtin_function(name) {
+                            if !kwargs.is_empty() {
+                                self.error(format!("функция {} не принимает именованных аргументов", name));
+                            }
+                            return self.check_builtin(&format!("функция {}", name), signature, &arg_types);
+                        }
+                    }
+                }
+                
+                // utils.f(...) и utils.Point(...)
+                if let Some((module, name)) = self.module_member(callee) {
+                    let qualified = format!("{}.{}", module, name);
+                    if let Some(def) = self.structs.get(&qualified).cloned() {
+                        return self.check_constructor(&def, &call);
+                    }
+                    let f = self.module_functions(&module).and_then(|exports| exports.get(&name)).cloned();
+                    return match f {
+                        Some(f) => self.check_call(&f, &call),
+                        None => None,
+                    };
+                }
+                
+                if !kwargs.is_empty() {
+                    self.error("именованные аргументы можно передавать только функциям и структурам по имени".to_string());
+                }
+                // s.upper(): метод строки
+                if let Expr::Field(object, method) = callee.as_ref() {
+                    match self.infer(object) {
+                        Some(Type::String) => return self.check_string_method(method, &arg_types),
+                        Some(Type::List(_)) if method == "len" && args.is_empty() => return Some(Type::Int),
+                        Some(Type::List(_)) => {
+                            self.error(format!("у списка нет метода {}", method));
+                            return None;
+                        },
+                        // Вызов поля-функции структуры проверяется ниже, как поле
+                        Some(Type::Custom(name)) if self.structs.contains_key(&name) => {},
+                        Some(Type::Generic(..) | Type::Optional(_) | Type::None) | None => {},
+                        Some(ty) => {
+                            self.error(format!("у значения типа {} нет метода {}", ty, method));
+                            return None;
+                        },
+                    }
+                }
+                match self.infer(callee) {
+                    Some(Type::Function(params, return_type)) => {
+                        // Именованных аргументов у значения типа fn нет, об этом уже сообщено выше
+                        if kwargs.is_empty() {
+                            let what = match callee.as_ref() {
+                                Expr::Variable(name) => format!("функция {}", name),
+                                _ => format!("функция типа {}", Type::Function(params.clone(), return_type.clone())),
+                            };
+                            let params: Vec<Param> = params.into_iter()
+                                .map(|ty| Param { name: String::new(), ty, default: None, variadic: false })
+                                .collect();
+                            self.check_arguments(&what, &[], &params, &call);
+                        }
+                        Some(*return_type)
+                    },
+                    ty @ Some(Type::Optional(_) | Type::None) => {
+                        self.check_present(&ty);
+                        None
+                    },
+                    Some(ty) => {
+                        self.error(format!("значение типа {} нельзя вызвать", ty));
+                        None
+                    },
+                    None => None,
+                }
+            },
+            Expr::Field(object, field) => {
+                if let Some((module, name)) = self.module_member(expr) {
+                    if let Some(ty) = self.global_exports.get(&module).and_then(|globals| globals.get(&name)) {
+                        return Some(ty.clone());
+                    }
+                    return match self.module_functions(&module).and_then(|exports| exports.get(&name)) {
+                        Some(f) if f.type_params.is_empty() => Some(f.function_type()),
+                        _ => None,
+                    };
+                }
+                
+                let object = self.infer(object);
+                self.check_present(&object);
+                let (name, args) = match object? {
+                    Type::Custom(name) => (name, vec![]),
+                    Type::Generic(name, args) => (name, args),
+                    Type::String if string_method(field).is_some() => {
+                        self.error(format!("метод строки {} нужно вызвать: s.{}()", field, field));
+                        return None;
+                    },
+                    Type::String => {
+                        self.error(format!("у строки нет поля {}", field));
+                        return None;
+                    },
+                    // О None уже сообщила check_present
+                    Type::Optional(_) | Type::None => return None,
+                    ty => {
+                        self.error(format!("у значения типа {} нет поля {}", ty, field));
+                        return None;
+                    },
+                };
+                let Some(def) = self.structs.get(&name).cloned() else {
+                    // Параметр типа T может оказаться чем угодно, поэтому полей у него нет
+                    if self.type_params.contains(&name) {
+                        self.error(format!("у значения обобщённого типа {} нет поля {}", name, field));
+                    }
+                    return None;
+                };
+                
+                match def.fields.iter().find(|(field_name, _)| field_name == field) {
+                    Some((_, ty)) => {
+                        let bindings = def.type_params.iter().cloned().zip(args).collect();
+                        Some(substitute(ty, &bindings))
+                    },
+                    None => {
+                        self.error(format!("у структуры {} нет поля {}", name, field));
+                        None
+                    }
+                }
+            },
+            Expr::Index(object, index) => {
+                let object = self.infer(object);
+                if !self.check_present(&object) {
+                    return None;
+                }
+                if let Some(index) = self.infer(index) {
+                    if !index.is_integer() {
+                        self.error(format!("индекс должен быть целым числом, получен {}", index));
+                    }
+                }
+                match object? {
+                    Type::List(item) => Some(*item),
+                    Type::String => Some(Type::String),
+                    other => {
+                        self.error(format!("значение типа {} не поддерживает индексацию", other));
+                        None
+                    }
+                }
+            },
+            Expr::Slice(object, start, end) => {
+                let object = self.infer(object);
+                if !self.check_present(&object) {
+                    return None;
+                }
+                for bound in [start, end].into_iter().flatten() {
+                    if let Some(bound) = self.infer(bound) {
+                        if !bound.is_integer() {
+                            self.error(format!("граница среза должна быть целым числом, получен {}", bound));
+                        }
+                    }
+                }
+                match object? {
+                    ty @ (Type::List(_) | Type::String) => Some(ty),
+                    other => {
+                        self.error(format!("значение типа {} не поддерживает срезы", other));
+                        None
+                    }
+                }
+            },
+            Expr::List(items) => {
+                // Целые литералы принимают тип остальных элементов: [x, 1] при x: u8 — list[u8]
+                let (literals, others): (Vec<&Expr>, Vec<&Expr>) = items.iter()
+                    .partition(|item| item.int_literal().is_some());
+                let mut item_type: Option<Type> = None;
+                for item in others {
+                    let ty = match self.infer(item) {
+                        Some(ty) => ty,
+                        None => continue,
+                    };
+                    item_type = match item_type {
+                        None => Some(ty),
+                        Some(Type::Int) if ty == Type::Float => Some(Type::Float),
+                        Some(Type::Float) if ty == Type::Int => Some(Type::Float),
+                        Some(known) if known == ty => Some(known),
+                        Some(known) => {
+                            self.error(format!("элементы списка имеют разные типы: {} и {}", known, ty));
+                            return None;
+                        }
+                    };
+                }
+                for item in literals {
+                    let ty = self.infer(item)?;
+                    match &item_type {
+                        None => item_type = Some(ty),
+                        Some(known) if known.is_numeric() => self.check_value(known, &ty, item, "элемент списка"),
+                        Some(known) => {
+                            self.error(format!("элементы списка имеют разные типы: {} и {}", known, ty));
+                            return None;
+                        }
+                    }
+                }
+                item_type.map(|item| Type::List(Box::new(item)))
+            },
+            Expr::Lambda(params, body) => {
+                // Типы параметров лямбды известны только из контекста: из аннотации
+                // переменной, типа параметра функции или типа результата
+                let expected = self.lambda_type.take();
+                self.scopes.push(HashMap::new());
+                let ty = match expected {
+                    Some(Type::Function(param_types, return_type)) if param_types.len() == params.len() => {
+                        for (name, ty) in params.iter().zip(&param_types) {
+                            self.declare(name, Some(ty.clone()));
+                        }
+                        if let Some(actual) = self.infer(body) {
+                            if *return_type != Type::Void {
+                                self.check_value(&return_type, &actual, body, "результат лямбды");
+                            }
+                        }
+                        Some(Type::Function(param_types, return_type))
+                    },
+                    expected => {
+                        if let Some(Type::Function(param_types, _)) = expected {
+                            self.error(format!(
+                                "лямбда принимает параметров: {}, а ожидается: {}",
+                                params.len(), param_types.len()
+                            ));
+                        }
+                        for name in params {
+                            self.declare(name, None);
+                        }
+                        self.infer(body);
+                        None
+                    },
+                };
+                self.scopes.pop();
+                ty
+            },
+            Expr::FString(parts) => {
+                for part in parts {
+                    if let FStringPart::Expr(expr, spec) = part {
+                        let ty = self.infer(expr);
+                        if let (Some(ty), Some(kind)) = (ty, spec.as_ref().and_then(|spec| spec.kind)) {
+                            self.check_format_kind(kind, &ty);
+                        }
+                    }
+                }
+                Some(Type::String)
+            },
+            Expr::If(condition, then_block, else_block) => {
+                self.infer(condition);
+                let narrowed = self.narrowing(condition);
+                
+                // В ветке, где переменная точно не None, она имеет тип без ?
+                self.scopes.push(HashMap::new());
+                if let Some((name, ty, true)) = &narrowed {
+                    self.declare(name, Some(ty.clone()));
+                }
+                self.check_block(then_block);
+                self.scopes.pop();
+                
+                if let Some(else_block) = else_block {
+                    self.scopes.push(HashMap::new());
+                    if let Some((name, ty, false)) = &narrowed {
+                        self.declare(name, Some(ty.clone()));
+                    }
+                    self.check_block(else_block);
+                    self.scopes.pop();
+                }
+                None
+            },
+            Expr::While(condition, body) => {
+                self.infer(condition);
+                self.check_block(body);
+                None
+            },
+            Expr::For(var, iterable, body) => {
+                let iterable = self.infer(iterable);
+                self.check_present(&iterable);
+                self.scopes.push(HashMap::new());
+                self.declare(var, None);
+                self.check_block(body);
+                self.scopes.pop();
+                None
+            }
+        }
+    }
+    
+    // Распознаёт обращение к имени модуля: utils.name. Проверяет, что имя
+    // существует и не приватное; при ошибке сообщает о ней и возвращает None.
+    fn module_member(&mut self, expr: &Expr) -> Option<(String, String)> {
+        let (module, name) = match expr {
+            Expr::Field(object, name) => match object.as_ref() {
+                Expr::Variable(module) if self.imported.contains(module) && self.lookup_scope(module).is_none() => {
+                    (module.clone(), name.clone())
+                },
+                _ => return None,
+            },
+            _ => return None,
+        };
+        
+        let qualified = format!("{}.{}", module, name);
+        let exists = self.structs.contains_key(&qualified)
+            || self.module_functions(&module).is_some_and(|exports| exports.contains_key(&name))
+            || self.global_exports.get(&module).is_some_and(|globals| globals.contains_key(&name));
+        if name.starts_with('_') {
+            self.error(format!("имя {} модуля {} является приватным", name, module));
+        } else if !exists {
+            self.error(format!("в модуле {} нет имени {}", module, name));
+        }
+        Some((module, name))
+    }
+    
+    fn check_call(&mut self, f: &FunctionDef, call: &Call) -> Option<Type> {
+        let what = format!("функция {}", f.name);
+        let bindings = self.check_arguments(&what, &f.type_params, &f.params, call)?;
+        resolve(&f.return_type, &f.type_params, &bindings)
+    }
+    
+    fn check_constructor(&mut self, def: &StructDef, call: &Call) -> Option<Type> {
+        let what = format!("структура {}", def.name);
+        let bindings = self.check_arguments(&what, &def.type_params, &def.constructor_params(), call)?;
+        
+        if def.type_params.is_empty() {
+            return Some(Type::Custom(def.name.clone()));
+        }
+        let args = def.type_params.iter()
+            .map(|name| bindings.get(name).cloned())
+            .collect::<Option<Vec<_>>>()?;
+        Some(Type::Generic(def.name.clone(), args))
+    }
+    
+    // Сопоставляет аргументы с параметрами, выводя параметры типа.
+    // Возвращает найденные привязки или None, если вызов ошибочен.
+    fn check_arguments(
+        &mut self,
+        what: &str,
+        type_params: &[String],
+        params: &[Param],
+        call: &Call,
+    ) -> Option<HashMap<String, Type>> {
+        let fixed: Vec<&Param> = params.iter().filter(|param| !param.variadic).collect();
+        let variadic = params.iter().find(|param| param.variadic);
+        let required = fixed.iter().filter(|param| param.default.is_none()).count();
+        
+        let too_many = call.args.len() > fixed.len() && variadic.is_none();
+        let too_few = call.kwargs.is_empty() && call.args.len() < required;
+        if too_many || too_few {
+            let expected = if variadic.is_some() {
+                format!("не менее {}", required)
+            } else if required == fixed.len() {
+                required.to_string()
+            } else {
+                format!("от {} до {}", required, fixed.len())
+            };
+            self.error(format!("{} ожидает аргументов: {}, передано: {}", what, expected, call.args.len()));
+            return None;
+        }
+        
+        // Четвёрки (тип параметра, тип аргумента, аргумент, описание аргумента для сообщений)
+        let mut pairs = Vec::new();
+        // Лямбды среди аргументов вместе с типами их параметров
+        let mut lambdas = Vec::new();
+        for (i, arg) in call.args.iter().enumerate() {
+            let param = match fixed.get(i) {
+                Some(param) => param.ty.clone(),
+                None => match variadic.map(|param| &param.ty) {
+                    Some(Type::List(item)) => item.as_ref().clone(),
+                    _ => continue,
+                },
+            };
+            if let Expr::Lambda(..) = call.exprs[i] {
+                lambdas.push((param.clone(), &call.exprs[i]));
+            }
+            pairs.push((param, arg, &call.exprs[i], format!("аргумент {}", i + 1)));
+        }
+        
+        let mut given: Vec<&str> = fixed.iter().take(call.args.len()).map(|param| param.name.as_str()).collect();
+        let mut valid = true;
+        for ((name, arg), (_, expr)) in call.kwargs.iter().zip(call.kwarg_exprs) {
+            match fixed.iter().find(|param| &param.name == name) {
+                Some(_) if given.contains(&name.as_str()) => {
+                    self.error(format!("{}: аргумент {} передан дважды", what, name));
+                    valid = false;
+                },
+                Some(param) => {
+                    given.push(&param.name);
+                    if let Expr::Lambda(..) = expr {
+                        lambdas.push((param.ty.clone(), expr));
+                    }
+                    pairs.push((param.ty.clone(), arg, expr, format!("аргумент {}", name)));
+                },
+                None => {
+                    self.error(format!("{}: нет параметра {}", what, name));
+                    valid = false;
+                }
+            }
+        }
+        for param in &fixed {
+            if param.default.is_none() && !given.contains(&param.name.as_str()) {
+                self.error(format!("{}: не передан аргумент {}", what, param.name));
+                valid = false;
+            }
+        }
+        if !valid {
+            return None;
+        }
+        
+        let mut bindings = HashMap::new();
+        for (param, arg, _, _) in &pairs {
+            if let Some(arg) = arg {
+                if let Err(message) = bind(param, arg, type_params, &mut bindings) {
+                    self.error(format!("{}: {}", what, message));
+                    return None;
+                }
+            }
+        }
+        
+        for (param, arg, expr, description) in &pairs {
+            if let Some(arg) = arg {
+                let expected = substitute(param, &bindings);
+                self.check_value(&expected, arg, expr, &format!("{}, {}", what, description));
+            }
+        }
+        // Лямбду проверяем, когда известны типы её параметров; если они зависят
+        // от параметра типа, который не удалось вывести, лямбда остаётся без типа
+        for (param, expr) in lambdas {
+            let expected = substitute(&param, &bindings);
+            if !mentions_any(&expected, type_params) {
+                self.infer_expecting(expr, &expected);
+            }
+        }
+        
+        Some(bindings)
+    }
+    
+    // Проверяет, что выражению можно присвоить значение, и возвращает его тип
+    fn check_target(&mut self, target: &Expr) -> Option<Type> {
+        if let Err(message) = self.check_lvalue(target) {
+            self.error(message);
+            return None;
+        }
+        
+        // Как в Python: поля глобального объекта менять можно, а саму переменную —
+        // только после global
+        if let Expr::Variable(name) = target {
+            if self.scope_depth(name) == Some(0) {
+                if self.constants.contains(name) {
+                    self.error(format!("нельзя изменить константу {}", name));
+                    return None;
+                }
+                if self.current_function.is_some() && !self.global_names.contains(name) {
+                    self.error(format!(
+                        "чтобы изменить глобальную переменную {} внутри функции, объявите её: global {}",
+                        name, name
+                    ));
+                    return None;
+                }
+            }
+        }
+        
+        if let Some(name) = target.root_variable() {
+            let depth = self.scope_depth(name).unwrap_or(0);
+            if depth > 0 && depth < self.function_scope {
+                self.error(format!(
+                    "вложенная функция не может изменить переменную {} внешней функции: она захвачена по значению",
+                    name
+                ));
+                return None;
+            }
+        }
+        self.infer(target)
+    }
+    
+    fn check_lvalue(&mut self, expr: &Expr) -> Result<(), String> {
+        match expr {
+            Expr::Variable(name) => {
+                if self.local_function(name).is_some() {
+                    Err(format!("нельзя присвоить значение {}: это вложенная функция", name))
+                } else if self.lookup_scope(name).is_some() {
+                    Ok(())
+                } else if self.functions.contains_key(name) || self.struct_names.contains_key(name) {
+                    Err(format!("нельзя присвоить значение {}: это не переменная", name))
+                } else if self.imported.contains(name) {
+                    Err(format!("нельзя присвоить значение модулю {}", name))
+                } else {
+                    Err(format!("переменная {} не объявлена, используйте let", name))
+                }
+            },
+            Expr::Field(object, field) => {
+                if let Expr::Variable(module) = object.as_ref() {
+                    if self.imported.contains(module) && self.lookup_scope(module).is_none() {
+                        return Err(format!("нельзя присвоить значение {}.{}", module, field));
+                    }
+                }
+                self.check_lvalue(object)
+            },
+            Expr::Index(object, _) => {
+                if self.infer(object) == Some(Type::String) {
+                    return Err("строки неизменяемы: нельзя присвоить значение символу".to_string());
+                }
+                self.check_lvalue(object)
+            },
+            _ => Err("левая часть присваивания должна быть переменной, полем или элементом списка".to_string()),
+        }
+    }
+    
+    // Опциональную переменную, проверенную на None, можно использовать как обычную.
+    // Возвращает имя, тип без ? и то, в какой ветке переменная не None.
+    fn narrowing(&self, condition: &Expr) -> Option<(String, Type, bool)> {
+        let (name, when_true) = condition.none_check()?;
+        match self.lookup_scope(name)? {
+            Some(Type::Optional(inner)) => Some((name.to_string(), *inner, when_true)),
+            _ => None,
+        }
+    }
+    
+    // Ветвь условного выражения, в которой проверенная на None переменная имеет тип без ?
+    fn infer_narrowed(&mut self, expr: &Expr, narrowed: Option<&(String, Type, bool)>, branch: bool) -> Option<Type> {
+        self.scopes.push(HashMap::new());
+        if let Some((name, ty, when_true)) = narrowed {
+            if *when_true == branch {
+                self.declare(name, Some(ty.clone()));
+            }
+        }
+        let ty = self.infer(expr);
+        self.scopes.pop();
+        ty
+    }
+    
+    fn check_comparison(&mut self, op: &BinOp, left: &Option<Type>, right: &Option<Type>) {
+        if matches!(op, BinOp::In | BinOp::NotIn) {
+            if !self.check_present(right) {
+                return;
+            }
+            match (left, right) {
+                (Some(Type::String), Some(Type::String)) => {},
+                (Some(item), Some(Type::List(element))) if assignable(element, item) || assignable(item, element) => {},
+                (Some(item), Some(container)) => {
+                    self.error(format!("оператор {} неприменим к типам {} и {}", op, item, container));
+                },
+                _ => {}
+            }
+            return;
+        }
+        if matches!(op, BinOp::Eq | BinOp::Ne) {
+            // С None можно сравнивать только опциональные значения
+            match (left, right) {
+                (Some(Type::None), Some(other)) | (Some(other), Some(Type::None))
+                    if !matches!(other, Type::Optional(_) | Type::None) =>
+                {
+                    self.error(format!("значение типа {} не может быть None", other));
+                },
+                _ => {}
+            }
+        } else {
+            self.check_present(left);
+            self.check_present(right);
+        }
+        // C++ сравнил бы -1 и 1u как беззнаковые числа
+        if let (Some(left), Some(right)) = (left, right) {
+            if left.is_integer() && right.is_integer() && common_numeric_type(left, right).is_none() {
+                self.error(format!(
+                    "нельзя сравнивать значения типов {} и {}: приведите одно из них через as",
+                    left, right
+                ));
+            }
+            // Строка из одного символа-литерала к этому месту уже стала char
+            if matches!((left, right), (Type::Char, Type::String) | (Type::String, Type::Char)) {
+                self.error(format!("нельзя сравнивать значения типов {} и {}", left, right));
+            }
+        }
+    }
+    
+    // Значение опционального типа нельзя использовать, пока оно не проверено на None
+    // Как infer, но лямбда получает типы параметров и результата из ожидаемого типа функции
+    fn infer_expecting(&mut self, expr: &Expr, expected: &Type) -> Option<Type> {
+        let target = match expected {
+            Type::Optional(inner) => inner.as_ref(),
+            _ => expected,
+        };
+        if let (Expr::Lambda(..), Type::Function(..)) = (expr, target) {
+            self.lambda_type = Some(target.clone());
+        }
+        self.infer(expr)
+    }
+    
+    fn check_present(&mut self, ty: &Option<Type>) -> bool {
+        match ty {
+            Some(Type::Optional(_)) | Some(Type::None) => {
+                self.error(format!(
+                    "значение типа {} может быть None: сначала проверьте его, например if x != None",
+                    ty.as_ref().unwrap()
+                ));
+                false
+            },
+            _ => true,
+        }
+    }
+    
+    fn check_string_method(&mut self, method: &str, args: &[Option<Type>]) -> Option<Type> {
+        match string_method(method) {
+            Some(signature) => self.check_builtin(&format!("метод строки {}", method), signature, args),
+            None => {
+                self.error(format!("у строки нет метода {}", method));
+                None
+            }
+        }
+    }
+    
+    // Вызов встроенной функции или метода строки; what — как назвать его в сообщении
+    fn check_builtin(&mut self, what: &str, (params, required, result): (Vec<Type>, usize, Type), args: &[Option<Type>]) -> Option<Type> {
+        if args.len() < required || args.len() > params.len() {
+            let expected = if required == params.len() {
+                required.to_string()
+            } else {
+                format!("от {} до {}", required, params.len())
+            };
+            self.error(format!("{} ожидает аргументов: {}, передано: {}", what, expected, args.len()));
+            return Some(result);
+        }
+        for (i, (param, arg)) in params.iter().zip(args).enumerate() {
+            if let Some(arg) = arg {
+                self.check_assignable(param, arg, &format!("{}, аргумент {}", what, i + 1));
+            }
+        }
+        Some(result)
+    }
+    
+    // print принимает любые значения и необязательные sep= и end=
+    fn check_print(&mut self, call: &Call) -> Option<Type> {
+        for (i, arg) in call.args.iter().enumerate() {
+            if *arg == Some(Type::Void) {
+                self.error(format!("print, аргумент {}: функция не возвращает значения", i + 1));
+            }
+        }
+        for (name, arg) in call.kwargs {
+            if name != "sep" && name != "end" {
+                self.error(format!("у print нет параметра {}", name));
+            } else if let Some(arg) = arg {
+                self.check_assignable(&Type::String, arg, &format!("print, параметр {}", name));
+            }
+        }
+        Some(Type::Void)
+    }
+    
+    fn check_format_kind(&mut self, kind: char, ty: &Type) {
+        let valid = match kind {
+            'b' | 'd' | 'o' | 'x' | 'X' => ty.is_integer() || *ty == Type::Bool,
+            'e' | 'E' | 'f' | 'F' | 'g' | 'G' | '%' => ty.is_numeric(),
+            's' => matches!(ty, Type::String),
+            _ => true,
+        };
+        if !valid {
+            self.error(format!("формат '{}' неприменим к значению типа {}", kind, ty));
+        }
+    }
+    
+    // Как check_assignable, но целый литерал принимает любой числовой тип, в который помещается,
+    // а дробный — также f32
+    fn check_value(&mut self, expected: &Type, actual: &Type, expr: &Expr, what: &str) {
+        let target = match expected {
+            Type::Optional(inner) => inner.as_ref(),
+            _ => expected,
+        };
+        let float_literal = *actual == Type::Float && expr.is_literal() && !matches!(expr, Expr::Cast(..));
+        if float_literal && *target == Type::F32 {
+            return;
+        }
+        if *target == Type::Char && self.char_literal(expr) {
+            return;
+        }
+        self.check_literal(expected, actual, expr.int_literal(), what);
+    }
+    
+    fn check_literal(&mut self, expected: &Type, actual: &Type, literal: Option<i64>, what: &str) {
+        let target = match expected {
+            Type::Optional(inner) => inner.as_ref(),
+            _ => expected,
+        };
+        match literal {
+            Some(value) if target.is_numeric() => self.check_range(value, target, what),
+            _ => self.check_assignable(expected, actual, what),
+        }
+    }
+    
+    fn check_range(&mut self, value: i64, ty: &Type, what: &str) {
+        if let Some((min, max)) = ty.int_range() {
+            if (value as i128) < min || (value as i128) > max {
+                self.error(format!("{}: значение {} не помещается в тип {}", what, value, ty));
+            }
+        }
+    }
+    
+    // Тип целого литерала в операции с другим операндом: литерал принимает его тип.
+    // Строка из одного символа рядом с char — тоже char: c == "a".
+    fn adapt_literal(&mut self, expr: &Expr, ty: Option<Type>, other: &Option<Type>) -> Option<Type> {
+        match (expr.int_literal(), other) {
+            (Some(value), Some(other)) if other.is_integer() => {
+                let target = promote(other);
+                self.check_range(value, &target, "литерал");
+                Some(target)
+            },
+            (None, Some(Type::Char)) if self.char_literal(expr) => Some(Type::Char),
+            _ => ty,
+        }
+    }
+    
+    // Строковый литерал из одного символа можно записать на месте char: let c: char = "a".
+    // После проверки модуля он заменяется кодом символа (см. convert_char_literals).
+    fn char_literal(&mut self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Literal(Literal::String(text)) if text.chars().count() == 1 => {
+                self.char_literals.insert(expr as *const Expr);
+                true
+            },
+            _ => false,
+        }
+    }
+    
+    fn check_assignable(&mut self, expected: &Type, actual: &Type, what: &str) {
+        if !assignable(expected, actual) {
+            self.error(format!("{}: ожидался тип {}, получен {}", what, expected, actual));
+        }
+    }
+    
+    fn declare(&mut self, name: &str, ty: Option<Type>) {
+        if self.imported.iter().any(|module| module == name) {
+            self.error(format!("имя {} уже занято импортированным модулем", name));
+        }
+        // Новое имя перекрывает вложенную функцию, объявленную в той же области
+        let depth = self.scopes.len().saturating_sub(1);
+        self.local_functions.retain(|(scope, f)| *scope != depth || f.name != name);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), ty);
+        }
+    }
+    
+    // Вложенная функция, которую обозначает имя в текущей точке
+    fn local_function(&self, name: &str) -> Option<&FunctionDef> {
+        let depth = self.scope_depth(name)?;
+        self.local_functions.iter()
+            .rev()
+            .find(|(scope, f)| *scope == depth && f.name == name)
+            .map(|(_, f)| f)
+    }
+    
+    // Some(None) — переменная объявлена, но её тип неизвестен
+    fn lookup_scope(&self, name: &str) -> Option<Option<Type>> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name).cloned())
+    }
+    
+    // Номер области видимости, в которой объявлена переменная; 0 — верхний уровень модуля
+    fn scope_depth(&self, name: &str) -> Option<usize> {
+        self.scopes.iter().rposition(|scope| scope.contains_key(name))
+    }
+    
+    fn error(&mut self, message: String) {
+        let message = match &self.current_function {
+            Some(name) => format!("в функции {}: {}", name, message),
+            None => message,
+        };
+        let message = if self.is_entry {
+            format!("строка {}: {}", self.line, message)
+        } else {
+            format!("модуль {}, строка {}: {}", self.module, self.line, message)
+        };
+        // Операнд цепочки сравнений проверяется дважды: с левым и с правым соседом
+        if !self.errors.contains(&message) {
+            self.errors.push(message);
+        }
+    }
+}
+
+// Методы строк: типы параметров, число обязательных из них и тип результата
+fn string_method(name: &str) -> Option<(Vec<Type>, usize, Type)> {
+    let strings = || Type::List(Box::new(Type::String));
+    Some(match name {
+        "len" => (vec![], 0, Type::Int),
+        "upper" | "lower" | "strip" => (vec![], 0, Type::String),
+        // Без разделителя строка делится по пробельным символам
+        "split" => (vec![Type::String], 0, strings()),
+        "join" => (vec![strings()], 1, Type::String),
+        "replace" => (vec![Type::String, Type::String], 2, Type::String),
+        "find" => (vec![Type::String], 1, Type::Int),
+        "startswith" | "endswith" => (vec![Type::String], 1, Type::Bool),
+        _ => return None,
+    })
+}
+
+// Встроенные функции ввода, разбора чисел и open: типы параметров, число обязательных и тип результата
+fn builtin_function(name: &str) -> Option<(Vec<Type>, usize, Type)> {
+    Some(match name {
+        "input" => (vec![Type::String], 0, Type::String),
+        // None в конце ввода
+        "read_line" => (vec![], 0, Type::Optional(Box::new(Type::String))),
+        "read_all" => (vec![], 0, Type::String),
+        "int" => (vec![Type::String], 1, Type::Int),
+        "float" => (vec![Type::String], 1, Type::Float),
+        // Строки файла без символов перевода строки
+        "open" => (vec![Type::String], 1, Type::List(Box::new(Type::String))),
+        _ => return None,
+    })
+}
+
+// Записывает выведенные типы в объявления локальных переменных без аннотации
+fn annotate_locals(stmts: &mut [Stmt], types: &HashMap<(usize, String), Type>) {
+    let mut line = 0;
+    for stmt in stmts {
+        match stmt {
+            Stmt::Line(n) => line = *n,
+            Stmt::Let(name, ty @ None, _) => {
+                *ty = types.get(&(line, name.clone())).cloned();
+            },
+            Stmt::Function(f) => annotate_locals(&mut f.body.0, types),
+            Stmt::Expr(expr) => annotate_block_exprs(expr, types),
+            Stmt::Try(body, handlers, finally) => {
+                annotate_locals(&mut body.0, types);
+                for handler in handlers {
+                    annotate_locals(&mut handler.body.0, types);
+                }
+                if let Some(finally) = finally {
+                    annotate_locals(&mut finally.0, types);
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+fn annotate_block_exprs(expr: &mut Expr, types: &HashMap<(usize, String), Type>) {
+    match expr {
+        Expr::If(_, then_block, else_block) => {
+            annotate_locals(&mut then_block.0, types);
+            if let Some(else_block) = else_block {
+                annotate_locals(&mut else_block.0, types);
+            }
+        },
+        Expr::While(_, body) | Expr::For(_, _, body) => annotate_locals(&mut body.0, types),
+        _ => {}
+    }
+}
+
+// Заменяет строковые литералы, принятые за char, приведением кода символа: "a" — 97 as char
+fn convert_char_literals(stmts: &mut [Stmt], literals: &HashSet<*const Expr>) {
+    if literals.is_empty() {
+        return;
+    }
+    for stmt in stmts {
+        match stmt {
+            Stmt::Expr(expr) | Stmt::Let(_, _, expr) | Stmt::Const(_, _, expr)
+            | Stmt::Return(Some(expr)) | Stmt::Raise(Some(expr)) => convert_char_literal(expr, literals),
+            Stmt::Assign(target, value) | Stmt::AugAssign(target, _, value) => {
+                convert_char_literal(target, literals);
+                convert_char_literal(value, literals);
+            },
+            Stmt::Function(f) => {
+                for default in f.params.iter_mut().filter_map(|param| param.default.as_mut()) {
+                    convert_char_literal(default, literals);
+                }
+                convert_char_literals(&mut f.body.0, literals);
+            },
+            Stmt::Try(body, handlers, finally) => {
+                convert_char_literals(&mut body.0, literals);
+                for handler in handlers {
+                    convert_char_literals(&mut handler.body.0, literals);
+                }
+                if let Some(finally) = finally {
+                    convert_char_literals(&mut finally.0, literals);
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+fn convert_char_literal(expr: &mut Expr, literals: &HashSet<*const Expr>) {
+    if literals.contains(&(expr as *const Expr)) {
+        if let Expr::Literal(Literal::String(text)) = expr {
+            let code = text.chars().next().map_or(0, |c| c as i64);
+            *expr = Expr::Cast(Box::new(Expr::Literal(Literal::Int(code))), Type::Char);
+        }
+        return;
+    }
+    match expr {
+        Expr::Literal(_) | Expr::Variable(_) => {},
+        Expr::BinaryOp(left, _, right) | Expr::Index(left, right) => {
+            convert_char_literal(left, literals);
+            convert_char_literal(right, literals);
+        },
+        Expr::Compare(first, rest) => {
+            convert_char_literal(first, literals);
+            for (_, operand) in rest {
+                convert_char_literal(operand, literals);
+            }
+        },
+        Expr::UnaryOp(_, operand) | Expr::Field(operand, _) | Expr::Cast(operand, _) | Expr::Lambda(_, operand) => {
+            convert_char_literal(operand, literals);
+        },
+        Expr::Call(callee, args, kwargs) => {
+            convert_char_literal(callee, literals);
+            for arg in args.iter_mut().chain(kwargs.iter_mut().map(|(_, arg)| arg)) {
+                convert_char_literal(arg, literals);
+            }
+        },
+        Expr::Slice(object, start, end) => {
+            convert_char_literal(object, literals);
+            for bound in [start, end].into_iter().flatten() {
+                convert_char_literal(bound, literals);
+            }
+        },
+        Expr::List(items) => {
+            for item in items {
+                convert_char_literal(item, literals);
+            }
+        },
+        Expr::FString(parts) => {
+            for part in parts {
+                if let FStringPart::Expr(expr, _) = part {
+                    convert_char_literal(expr, literals);
+                }
+            }
+        },
+        Expr::If(condition, then_block, else_block) => {
+            convert_char_literal(condition, literals);
+            convert_char_literals(&mut then_block.0, literals);
+            if let Some(else_block) = else_block {
+                convert_char_literals(&mut else_block.0, literals);
+            }
+        },
+        Expr::Conditional(condition, then_expr, else_expr) => {
+            convert_char_literal(condition, literals);
+            convert_char_literal(then_expr, literals);
+            convert_char_literal(else_expr, literals);
+        },
+        Expr::While(condition, body) | Expr::For(_, condition, body) => {
+            convert_char_literal(condition, literals);
+            convert_char_literals(&mut body.0, literals);
+        },
+    }
+}
+
+// Можно ли присвоить значение типа `actual` месту типа `expected`. Неявно
+// допускаются только преобразования чисел без потери значения и int в float.
+fn assignable(expected: &Type, actual: &Type) -> bool {
+    match (expected, actual) {
+        _ if expected == actual => true,
+        (Type::Optional(_), Type::None) => true,
+        (Type::Optional(inner), Type::Optional(actual)) => assignable(inner, actual),
+        (Type::Optional(inner), _) => assignable(inner, actual),
+        _ => widens(actual, expected),
+    }
+}
+
+// Расширяющее преобразование чисел: в более широкий целый тип, из беззнакового
+// в более широкий знаковый, из целого в float, из f32 в float; в f32 — из целых до 16 бит
+fn widens(from: &Type, to: &Type) -> bool {
+    match (from.int_bits(), to.int_bits()) {
+        (Some(from_bits), Some(to_bits)) => match (from.is_unsigned(), to.is_unsigned()) {
+            (false, true) => false,
+            (true, false) => to_bits > from_bits,
+            _ => to_bits >= from_bits,
+        },
+        (Some(bits), None) => *to == Type::Float || (*to == Type::F32 && bits <= 16),
+        (None, _) => *from == Type::F32 && *to == Type::Float,
+    }
+}
+
+// Как в C++, целые уже 32 бит в арифметике расширяются до i32
+fn promote(ty: &Type) -> Type {
+    match ty {
+        Type::I8 | Type::I16 | Type::U8 | Type::U16 => Type::I32,
+        _ => ty.clone(),
+    }
+}
+
+// Общий тип двух чисел в арифметике. Знаковое и беззнаковое число смешивать нельзя,
+// если знаковый тип не вмещает все значения беззнакового: C++ сделал бы результат беззнаковым.
+fn common_numeric_type(left: &Type, right: &Type) -> Option<Type> {
+    let (left, right) = (promote(left), promote(right));
+    match (left.is_float(), right.is_float()) {
+        (true, true) => Some(if left == Type::Float || right == Type::Float { Type::Float } else { Type::F32 }),
+        (true, false) => Some(left),
+        (false, true) => Some(right),
+        _ if left == right => Some(left),
+        _ if widens(&left, &right) => Some(right),
+        _ if widens(&right, &left) => Some(left),
+        _ => None,
+    }
+}
+
+// Тип результата арифметической операции; None, если она неприменима
+fn arithmetic_type(op: &BinOp, left: &Type, right: &Type) -> Option<Type> {
+    if *left == Type::String && *right == Type::String && matches!(op, BinOp::Add) {
+        return Some(Type::String);
+    }
+    if !left.is_numeric() || !right.is_numeric() {
+        return None;
+    }
+    match op {
+        // Тип сдвига определяется левым операндом
+        BinOp::Shl | BinOp::Shr if left.is_integer() && right.is_integer() => Some(promote(left)),
+        BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor | BinOp::Shl | BinOp::Shr => {
+            if left.is_integer() && right.is_integer() {
+                common_numeric_type(left, right)
+            } else {
+                None
+            }
+        },
+        _ => common_numeric_type(left, right),
+    }
+}
+
+// Привязывает параметры типа из `param` к соответствующим частям `arg`
+fn bind(
+    param: &Type,
+    arg: &Type,
+    type_params: &[String],
+    bindings: &mut HashMap<String, Type>,
+) -> Result<(), String> {
+    match (param, arg) {
+        (Type::Custom(name), _) if type_params.contains(name) => {
+            match bindings.get(name) {
+                Some(bound) if bound != arg => Err(format!(
+                    "параметр типа {} не может быть одновременно {} и {}",
+                    name, bound, arg
+                )),
+                Some(_) => Ok(()),
+                None => {
+                    bindings.insert(name.clone(), arg.clone());
+                    Ok(())
+                }
+            }
+        },
+        (Type::Generic(name, params), Type::Generic(arg_name, args))
+            if name == arg_name && params.len() == args.len() =>
+        {
+            for (param, arg) in params.iter().zip(args) {
+                bind(param, arg, type_params, bindings)?;
+            }
+            Ok(())
+        },
+        (Type::List(param), Type::List(arg)) => bind(param, arg, type_params, bindings),
+        (Type::Optional(param), Type::Optional(arg)) => bind(param, arg, type_params, bindings),
+        (Type::Optional(_), Type::None) => Ok(()),
+        (Type::Optional(param), _) => bind(param, arg, type_params, bindings),
+        (Type::Function(params, return_type), Type::Function(args, arg_return))
+            if params.len() == args.len() =>
+        {
+            for (param, arg) in params.iter().zip(args) {
+                bind(param, arg, type_params, bindings)?;
+            }
+            bind(return_type, arg_return, type_params, bindings)
+        },
+        _ => Ok(()),
+    }
+}
+
+fn substitute(ty: &Type, bindings: &HashMap<String, Type>) -> Type {
+    match ty {
+        Type::Custom(name) => bindings.get(name).cloned().unwrap_or_else(|| ty.clone()),
+        Type::Generic(name, args) => Type::Generic(
+            name.clone(),
+            args.iter().map(|arg| substitute(arg, bindings)).collect(),
+        ),
+        Type::List(item) => Type::List(Box::new(substitute(item, bindings))),
+        Type::Optional(inner) => Type::Optional(Box::new(substitute(inner, bindings))),
+        Type::Function(params, return_type) => Type::Function(
+            params.iter().map(|param| substitute(param, bindings)).collect(),
+            Box::new(substitute(return_type, bindings)),
+        ),
+        _ => ty.clone(),
+    }
+}
+
+// Подставляет привязки; None, если какой-то параметр типа так и не был выведен
+fn resolve(ty: &Type, type_params: &[String], bindings: &HashMap<String, Type>) -> Option<Type> {
+    let resolved = substitute(ty, bindings);
+    if mentions_any(&resolved, type_params) {
+        None
+    } else {
+        Some(resolved)
+    }
+}
+
+fn mentions_any(ty: &Type, names: &[String]) -> bool {
+    match ty {
+        Type::Custom(name) => names.contains(name),
+        Type::Generic(_, args) => args.iter().any(|arg| mentions_any(arg, names)),
+        Type::List(item) | Type::Optional(item) => mentions_any(item, names),
+        Type::Function(params, return_type) => {
+            params.iter().any(|param| mentions_any(param, names)) || mentions_any(return_type, names)
+        },
+        _ => false,
+    }
+}
@@ -0,0 +1,35 @@
+use crate::ast::{FunctionDef, Stmt};
+use crate::modules::parse_source;
+
+// Стандартные модули встроены в компилятор: import fs не ищет файл fs.cst.
+// Функции модуля объявлены заголовками, как в pyimport, а реализованы в среде
+// выполнения C++, которую генератор кода добавляет, только если модуль импортирован.
+pub const STD_MODULES: &[&str] = &["fs"];
+
+// Файлы и каталоги; ошибки файловой системы — исключение IOError
+const FS_DECLARATIONS: &str = "pyimport fs:
+    def read_file(path: string) -> string
+    def write_file(path: string, text: string) -> void
+    def append_file(path: string, text: string) -> void
+    def exists(path: string) -> bool
+    def list_dir(path: string) -> list[string]
+    def remove(path: string) -> void
+    def mkdir(path: string) -> void
+";
+
+pub fn is_std_module(name: &str) -> bool {
+    STD_MODULES.contains(&name)
+}
+
+// Функции стандартного модуля
+pub fn std_module(name: &str) -> Option<Vec<FunctionDef>> {
+    let declarations = match name {
+        "fs" => FS_DECLARATIONS,
+        _ => return None,
+    };
+    let ast = parse_source(declarations).expect("standard module declarations must parse");
+    ast.into_iter().find_map(|stmt| match stmt {
+        Stmt::PyImport(_, functions) => Some(functions),
+        _ => None,
+    })
+}